@@ -0,0 +1,278 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! The `#[xpc_service]` attribute. Use it through `xpc_serde::xpc_service`,
+//! which documents it.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+	ext::IdentExt, parse_macro_input, parse_quote, Attribute, FnArg, ItemTrait, Pat, ReturnType,
+	TraitItem, Type,
+};
+
+#[proc_macro_attribute]
+pub fn xpc_service(attr: TokenStream, item: TokenStream) -> TokenStream {
+	if !attr.is_empty() {
+		return syn::Error::new(
+			proc_macro2::Span::call_site(),
+			"#[xpc_service] takes no arguments",
+		)
+		.to_compile_error()
+		.into();
+	}
+	let service = parse_macro_input!(item as ItemTrait);
+	match expand(&service) {
+		Ok(tokens) => tokens.into(),
+		Err(error) => error.to_compile_error().into(),
+	}
+}
+
+/// A method of the service trait.
+struct Method {
+	/// The method's doc comments, copied onto the client method.
+	docs: Vec<Attribute>,
+	name: Ident,
+	args: Vec<(Ident, Type)>,
+	ret: Type,
+}
+
+fn method(item: &TraitItem) -> syn::Result<Method> {
+	let method = match item {
+		TraitItem::Method(method) => method,
+		item => {
+			return Err(syn::Error::new_spanned(
+				item,
+				"xpc_service traits can only contain methods",
+			))
+		}
+	};
+	let sig = &method.sig;
+	if sig.asyncness.is_some() || sig.unsafety.is_some() || sig.abi.is_some() {
+		return Err(syn::Error::new_spanned(
+			sig,
+			"xpc_service methods can't be async, unsafe or extern",
+		));
+	}
+	if !sig.generics.params.is_empty() || sig.generics.where_clause.is_some() {
+		return Err(syn::Error::new_spanned(
+			&sig.generics,
+			"xpc_service methods can't be generic",
+		));
+	}
+	let mut inputs = sig.inputs.iter();
+	match inputs.next() {
+		Some(FnArg::Receiver(receiver))
+			if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+		_ => {
+			return Err(syn::Error::new_spanned(
+				sig,
+				"xpc_service methods must take &self",
+			))
+		}
+	}
+	let args = inputs
+		.map(|input| match input {
+			FnArg::Typed(arg) => match &*arg.pat {
+				Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+					Ok((pat.ident.clone(), (*arg.ty).clone()))
+				}
+				pat => Err(syn::Error::new_spanned(
+					pat,
+					"xpc_service arguments must be plain names",
+				)),
+			},
+			FnArg::Receiver(receiver) => {
+				Err(syn::Error::new_spanned(receiver, "unexpected receiver"))
+			}
+		})
+		.collect::<syn::Result<Vec<_>>>()?;
+	let ret = match &sig.output {
+		ReturnType::Default => parse_quote!(()),
+		ReturnType::Type(_, ty) => (**ty).clone(),
+	};
+	Ok(Method {
+		docs: method
+			.attrs
+			.iter()
+			.filter(|attr| attr.path.is_ident("doc"))
+			.cloned()
+			.collect(),
+		name: sig.ident.clone(),
+		args,
+		ret,
+	})
+}
+
+fn expand(service: &ItemTrait) -> syn::Result<TokenStream2> {
+	if !service.generics.params.is_empty() || service.generics.where_clause.is_some() {
+		return Err(syn::Error::new_spanned(
+			&service.generics,
+			"xpc_service traits can't be generic",
+		));
+	}
+	let methods = service
+		.items
+		.iter()
+		.map(method)
+		.collect::<syn::Result<Vec<_>>>()?;
+
+	let vis = &service.vis;
+	let name = &service.ident;
+	let client = format_ident!("{}Client", name);
+	let server = format_ident!("{}Server", name);
+	let request = format_ident!("__{}Request", name);
+	let reply = format_ident!("__{}Reply", name);
+	let client_doc = format!("Calls the methods of [{}] over a transport.", name);
+	let server_doc = format!(
+		"Answers calls to the methods of [{}] with a service implementing it.",
+		name
+	);
+
+	let method_names = methods
+		.iter()
+		.map(|method| method.name.unraw().to_string())
+		.collect::<Vec<_>>();
+	let request_variants = methods.iter().map(|Method { name, args, .. }| {
+		let (arg, arg_ty): (Vec<_>, Vec<_>) = args.iter().cloned().unzip();
+		quote!(#name { #(#arg: #arg_ty,)* })
+	});
+	let reply_variants = methods
+		.iter()
+		.map(|Method { name, ret, .. }| quote!(#name(#ret)));
+	let client_methods = methods.iter().map(
+		|Method {
+		     docs,
+		     name,
+		     args,
+		     ret,
+		 }| {
+			let (arg, arg_ty): (Vec<_>, Vec<_>) = args.iter().cloned().unzip();
+			quote! {
+				#(#docs)*
+				#vis fn #name(
+					&mut self
+					#(, #arg: #arg_ty)*
+				) -> ::std::result::Result<#ret, ::xpc_serde::error::RpcError> {
+					let request = #request::#name { #(#arg,)* };
+					match self.inner.call(&request)? {
+						#reply::#name(value) => ::std::result::Result::Ok(value),
+						#[allow(unreachable_patterns)]
+						_ => ::std::result::Result::Err(::xpc_serde::error::RpcError::UnexpectedReply),
+					}
+				}
+			}
+		},
+	);
+	let dispatch_arms = methods.iter().map(|Method { name, args, .. }| {
+		let arg = args.iter().map(|(arg, _)| arg).collect::<Vec<_>>();
+		quote! {
+			#request::#name { #(#arg,)* } => #reply::#name(__xpc_service.#name(#(#arg),*))
+		}
+	});
+
+	Ok(quote! {
+		#service
+
+		#[doc(hidden)]
+		#[allow(non_camel_case_types)]
+		#[derive(::xpc_serde::__private::serde::Serialize, ::xpc_serde::__private::serde::Deserialize)]
+		#[serde(crate = "::xpc_serde::__private::serde")]
+		enum #request {
+			#(#request_variants,)*
+		}
+
+		#[doc(hidden)]
+		#[allow(non_camel_case_types)]
+		#[derive(::xpc_serde::__private::serde::Serialize, ::xpc_serde::__private::serde::Deserialize)]
+		#[serde(crate = "::xpc_serde::__private::serde")]
+		enum #reply {
+			#(#reply_variants,)*
+		}
+
+		#[doc = #client_doc]
+		#vis struct #client<T> {
+			inner: ::xpc_serde::rpc::Client<T>,
+		}
+
+		impl<T> #client<T>
+		where
+			T: ::xpc_serde::rpc::Transport,
+		{
+			#vis fn new(transport: T) -> Self {
+				Self {
+					inner: ::xpc_serde::rpc::Client::new(transport),
+				}
+			}
+
+			#(#client_methods)*
+
+			#vis fn into_inner(self) -> T {
+				self.inner.into_inner()
+			}
+		}
+
+		#[doc = #server_doc]
+		#vis struct #server<T, S> {
+			inner: ::xpc_serde::rpc::Server<T>,
+			service: S,
+		}
+
+		impl<T, S> #server<T, S>
+		where
+			T: ::xpc_serde::rpc::Transport,
+			S: #name,
+		{
+			#vis fn new(transport: T, service: S) -> Self {
+				Self {
+					inner: ::xpc_serde::rpc::Server::new(transport),
+					service,
+				}
+			}
+
+			/// Handles a single request, returning `false` if the connection was
+			/// closed before one arrived.
+			///
+			/// If the request can't be handled, the client is sent an error reply
+			/// before the error is returned.
+			#vis fn handle_one(&mut self) -> ::std::result::Result<bool, ::xpc_serde::error::RpcError> {
+				let service = &self.service;
+				self.inner.handle_message(|request| Self::dispatch(service, request))
+			}
+
+			/// Handles requests until the connection is closed. Requests that can't
+			/// be handled are answered with an error and skipped; only a connection
+			/// error stops the server early.
+			#vis fn serve(&mut self) -> ::std::result::Result<(), ::xpc_serde::error::RpcError> {
+				let service = &self.service;
+				self.inner.serve_messages(|request| Self::dispatch(service, request))
+			}
+
+			#vis fn into_inner(self) -> (T, S) {
+				(self.inner.into_inner(), self.service)
+			}
+
+			fn dispatch(
+				__xpc_service: &S,
+				request: ::xpc_serde::__private::Message,
+			) -> ::std::result::Result<#reply, ::xpc_serde::error::RpcError> {
+				if let ::std::option::Option::Some(method) =
+					::xpc_serde::__private::unknown_method(&request, &[#(#method_names),*])
+				{
+					return ::std::result::Result::Err(
+						::xpc_serde::error::RpcError::UnknownMethod(method),
+					);
+				}
+				::std::result::Result::Ok(match ::xpc_serde::deserialize(request)? {
+					#(#dispatch_arms,)*
+				})
+			}
+		}
+	})
+}
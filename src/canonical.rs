@@ -0,0 +1,59 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! A canonical encoding of messages, for signing, caching and deduplicating
+//! them.
+//!
+//! [Message::Dictionary] is a [HashMap](std::collections::HashMap), so equal
+//! messages can iterate in different orders, and the same value serialized
+//! with different [IntegerEncoding](crate::IntegerEncoding)s gives different
+//! integer types. The canonical form is the [wire](crate::wire) encoding of a
+//! message, with:
+//!
+//! - dictionary keys sorted by their bytes;
+//! - non-negative [Message::Int64]s written as [Message::Uint64]s, so an
+//!   integer's type only depends on its sign;
+//! - `-0.0` written as `0.0`, and every NaN written as the same quiet NaN.
+//!
+//! Messages that are equal after that normalization have the same canonical
+//! bytes. With the `digest` feature, `digest` hashes them with SHA-256.
+//!
+//! ```
+//! # #[cfg(feature = "digest")] {
+//! use xpc_serde::{canonical, IntegerEncoding, SerializerConfig};
+//!
+//! let signed = SerializerConfig::new()
+//!     .integer_encoding(IntegerEncoding::PreferInt64)
+//!     .serialize(&(1u8, 2u8))
+//!     .unwrap();
+//! let unsigned = xpc_serde::serialize(&(1u8, 2u8)).unwrap();
+//! assert_ne!(signed, unsigned);
+//! assert_eq!(
+//!     canonical::digest(&signed).unwrap(),
+//!     canonical::digest(&unsigned).unwrap()
+//! );
+//! # }
+//! ```
+
+use crate::{error::WireError, wire};
+#[cfg(feature = "digest")]
+use sha2::{Digest, Sha256};
+use xpc_connection::Message;
+
+/// Encodes a message in its canonical form. The result is valid
+/// [wire](crate::wire) format, and can be read back with
+/// [wire::decode](crate::wire::decode).
+pub fn canonical_bytes(message: &Message) -> Result<Vec<u8>, WireError> {
+	wire::encode_with(message, true)
+}
+
+/// The SHA-256 digest of a message's [canonical_bytes].
+#[cfg(feature = "digest")]
+pub fn digest(message: &Message) -> Result<[u8; 32], WireError> {
+	Ok(Sha256::digest(&canonical_bytes(message)?).into())
+}
@@ -0,0 +1,135 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::error::DateOutOfRange;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+	convert::TryFrom,
+	fmt,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The newtype struct name [Date] serializes with, so the serializer and
+/// deserializer can tell dates apart from other integers.
+pub(crate) const DATE_NAME: &str = "$xpc_serde::Date";
+
+/// A point in time, stored the way XPC stores dates: as nanoseconds since the
+/// Unix epoch, covering roughly the years 1678 to 2262.
+///
+/// [Message](xpc_connection::Message) has no date kind, so dates are
+/// serialized as a [Message::Int64](xpc_connection::Message::Int64) of
+/// nanoseconds, whatever [IntegerEncoding](crate::IntegerEncoding) is set to.
+/// This matches how the [wire](crate::wire) decoder and plist conversion
+/// represent dates. Other serde formats see a plain `i64`. Under
+/// [NumericCoercion::Lossless](crate::NumericCoercion::Lossless), a
+/// [Message::Uint64](xpc_connection::Message::Uint64) that fits is read as a
+/// date too.
+///
+/// This stands in for XPC's native date type until xpc-connection can carry
+/// one; peers expecting `XPC_TYPE_DATE` will see an int64 instead.
+///
+/// ```
+/// use std::{convert::TryFrom, time::SystemTime};
+/// use xpc_serde::Date;
+///
+/// let now = SystemTime::now();
+/// let date = Date::try_from(now).unwrap();
+/// assert_eq!(SystemTime::from(date), now);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date(i64);
+
+impl Date {
+	/// The Unix epoch, 1970-01-01 00:00:00 UTC.
+	pub const UNIX_EPOCH: Date = Date(0);
+
+	pub fn from_nanoseconds(nanoseconds: i64) -> Self {
+		Self(nanoseconds)
+	}
+
+	/// Nanoseconds since the Unix epoch, negative for earlier dates.
+	pub fn nanoseconds(self) -> i64 {
+		self.0
+	}
+}
+
+impl TryFrom<SystemTime> for Date {
+	type Error = DateOutOfRange;
+
+	fn try_from(time: SystemTime) -> Result<Self, Self::Error> {
+		let nanoseconds = match time.duration_since(UNIX_EPOCH) {
+			Ok(duration) => i64::try_from(duration.as_nanos()).ok(),
+			Err(error) => i64::try_from(error.duration().as_nanos())
+				.ok()
+				.and_then(i64::checked_neg),
+		};
+		nanoseconds.map(Self).ok_or(DateOutOfRange)
+	}
+}
+
+impl From<Date> for SystemTime {
+	fn from(date: Date) -> Self {
+		let duration = Duration::from_nanos(date.0.unsigned_abs());
+		if date.0 < 0 {
+			UNIX_EPOCH - duration
+		} else {
+			UNIX_EPOCH + duration
+		}
+	}
+}
+
+impl Serialize for Date {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_newtype_struct(DATE_NAME, &self.0)
+	}
+}
+
+impl<'de> Deserialize<'de> for Date {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_newtype_struct(DATE_NAME, DateVisitor)
+	}
+}
+
+struct DateVisitor;
+
+impl<'de> de::Visitor<'de> for DateVisitor {
+	type Value = Date;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a date in nanoseconds since the Unix epoch")
+	}
+
+	fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		i64::deserialize(deserializer).map(Date)
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Ok(Date(v))
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		i64::try_from(v)
+			.map(Date)
+			.map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+	}
+}
@@ -6,45 +6,478 @@
 	file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use crate::{error::DeserializeError, xpc_message_to_type};
+use crate::{
+	date::DATE_NAME,
+	error::{DeserializeError, PathSegment, XpcPeerError, PEER_ERROR_NAME},
+	uuid::UUID_NAME,
+	xpc_message_to_type, EnumRepresentation, WideIntegerEncoding, ENUM_TYPE_KEY, ENUM_VALUE_KEY,
+};
 use serde::de::{
-	self, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
-	VariantAccess,
+	self, value::BytesDeserializer, DeserializeSeed, Deserializer, EnumAccess, IntoDeserializer,
+	MapAccess, SeqAccess, VariantAccess,
 };
 use std::{
-	collections::{HashMap, VecDeque},
-	ffi::CString,
+	collections::hash_map,
+	convert::TryFrom,
+	ffi::{CStr, CString},
+	iter, slice, vec,
 };
 use xpc_connection::Message;
 
-pub(crate) struct XpcDeserializer {
-	pub(crate) message: Message,
+/// Converts an integer received from the peer into a narrower type, failing
+/// with [DeserializeError::OutOfRange] instead of truncating.
+fn narrow<T, U>(expected: &'static str, value: U) -> Result<T, DeserializeError>
+where
+	T: TryFrom<U>,
+	U: Into<i128> + Copy,
+{
+	T::try_from(value).map_err(|_| DeserializeError::OutOfRange {
+		expected,
+		value: value.into(),
+	})
+}
+
+/// How the deserializer treats integers and doubles that don't match the
+/// requested Rust type exactly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumericCoercion {
+	/// Signed integers only accept [Message::Int64], unsigned integers only
+	/// accept [Message::Uint64], and floats only accept [Message::Double].
+	Strict,
+	/// Any integer message is accepted by any integer type as long as the
+	/// value fits, and by floats as long as it is exactly representable.
+	#[default]
+	Lossless,
+}
+
+/// Which messages a [Uuid](crate::Uuid) is deserialized from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UuidCoercion {
+	/// Only [Message::Uuid] is accepted.
+	#[default]
+	Strict,
+	/// [Message::Data] holding 16 bytes and [Message::String] holding a
+	/// hyphenated UUID are accepted as well, for peers that don't send the
+	/// native uuid type.
+	Lenient,
+}
+
+/// Options controlling how a [Message] is deserialized.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeserializerConfig {
+	pub(crate) numeric_coercion: NumericCoercion,
+	pub(crate) uuid_coercion: UuidCoercion,
+	pub(crate) wide_integer_encoding: WideIntegerEncoding,
+	pub(crate) enum_representation: EnumRepresentation,
+}
+
+impl DeserializerConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets how integers and doubles are coerced between each other.
+	/// Defaults to [NumericCoercion::Lossless].
+	pub fn numeric_coercion(mut self, numeric_coercion: NumericCoercion) -> Self {
+		self.numeric_coercion = numeric_coercion;
+		self
+	}
+
+	/// Sets which messages UUIDs are accepted from.
+	/// Defaults to [UuidCoercion::Strict].
+	pub fn uuid_coercion(mut self, uuid_coercion: UuidCoercion) -> Self {
+		self.uuid_coercion = uuid_coercion;
+		self
+	}
+
+	/// Sets how [i128] and [u128] values are expected to be encoded.
+	/// Defaults to [WideIntegerEncoding::Data].
+	pub fn wide_integer_encoding(mut self, wide_integer_encoding: WideIntegerEncoding) -> Self {
+		self.wide_integer_encoding = wide_integer_encoding;
+		self
+	}
+
+	/// Sets how enum variants are expected to be laid out.
+	/// Defaults to [EnumRepresentation::ExternallyTagged].
+	pub fn enum_representation(mut self, enum_representation: EnumRepresentation) -> Self {
+		self.enum_representation = enum_representation;
+		self
+	}
+
+	/// Deserializes a [Message] using this configuration.
+	pub fn deserialize<'de, T>(&self, message: Message) -> Result<T, DeserializeError>
+	where
+		T: de::Deserialize<'de>,
+	{
+		T::deserialize(XpcDeserializer {
+			input: message,
+			config: *self,
+		})
+	}
+
+	/// Deserializes a reply using this configuration, or returns the
+	/// [XpcPeerError] libxpc delivered in its place as a [Message::Error].
+	///
+	/// Use this instead of deserializing into `Result<T, XpcPeerError>`, which
+	/// only reads results this crate serialized, and rejects a bare
+	/// [Message::Error].
+	pub fn deserialize_reply<'de, T>(
+		&self,
+		message: Message,
+	) -> Result<Result<T, XpcPeerError>, DeserializeError>
+	where
+		T: de::Deserialize<'de>,
+	{
+		match message {
+			Message::Error(error) => Ok(Err(error.into())),
+			message => self.deserialize(message).map(Ok),
+		}
+	}
+
+	/// Deserializes a borrowed [Message] using this configuration, allowing
+	/// strings and bytes to be borrowed from it without copying.
+	pub fn deserialize_ref<'de, T>(&self, message: &'de Message) -> Result<T, DeserializeError>
+	where
+		T: de::Deserialize<'de>,
+	{
+		T::deserialize(XpcDeserializer {
+			input: message,
+			config: *self,
+		})
+	}
+
+	pub(crate) fn integer<T>(
+		&self,
+		message: &Message,
+		expected: &'static str,
+		signed: bool,
+	) -> Result<T, DeserializeError>
+	where
+		T: TryFrom<i64> + TryFrom<u64>,
+	{
+		let lossless = self.numeric_coercion == NumericCoercion::Lossless;
+		match *message {
+			Message::Int64(v) if signed || lossless => narrow(expected, v),
+			Message::Uint64(v) if !signed || lossless => narrow(expected, v),
+			_ => Err(DeserializeError::Unexpected(
+				if signed { "i64" } else { "u64" },
+				xpc_message_to_type(message),
+			)),
+		}
+	}
+
+	/// Decodes an [i128] or [u128] according to the configured
+	/// [WideIntegerEncoding]. With [NumericCoercion::Lossless], plain XPC
+	/// integers are accepted as well.
+	pub(crate) fn wide<T>(
+		&self,
+		message: &Message,
+		expected: &'static str,
+		from_bits: fn(u128) -> T,
+	) -> Result<T, DeserializeError>
+	where
+		T: TryFrom<i64> + TryFrom<u64> + std::str::FromStr,
+	{
+		let encoding = self.wide_integer_encoding;
+		match message {
+			Message::Int64(_) | Message::Uint64(_)
+				if self.numeric_coercion == NumericCoercion::Lossless =>
+			{
+				self.integer(message, expected, true)
+			}
+			Message::Data(data) if encoding == WideIntegerEncoding::Data => {
+				let bytes = <[u8; 16]>::try_from(data.as_slice()).map_err(|_| {
+					DeserializeError::InvalidLength {
+						expected: 16,
+						got: data.len(),
+					}
+				})?;
+				Ok(from_bits(u128::from_be_bytes(bytes)))
+			}
+			Message::String(s) if encoding == WideIntegerEncoding::String => s
+				.to_str()?
+				.parse()
+				.map_err(|_| DeserializeError::InvalidDecimal(expected)),
+			Message::Array(halves) if encoding == WideIntegerEncoding::Halves => {
+				match halves.as_slice() {
+					[Message::Uint64(high), Message::Uint64(low)] => {
+						Ok(from_bits(u128::from(*high) << 64 | u128::from(*low)))
+					}
+					[Message::Uint64(_), other] | [other, _] => Err(DeserializeError::Unexpected(
+						"u64",
+						xpc_message_to_type(other),
+					)),
+					_ => Err(DeserializeError::InvalidLength {
+						expected: 2,
+						got: halves.len(),
+					}),
+				}
+			}
+			_ => Err(DeserializeError::Unexpected(
+				match encoding {
+					WideIntegerEncoding::Data => "bytes",
+					WideIntegerEncoding::String => "string",
+					WideIntegerEncoding::Halves => "array",
+				},
+				xpc_message_to_type(message),
+			)),
+		}
+	}
+
+	pub(crate) fn float(
+		&self,
+		message: &Message,
+		expected: &'static str,
+		is_exact: impl Fn(i128) -> bool,
+	) -> Result<f64, DeserializeError> {
+		let lossless = self.numeric_coercion == NumericCoercion::Lossless;
+		let value = match *message {
+			Message::Double(v) => return Ok(v),
+			Message::Int64(v) if lossless => i128::from(v),
+			Message::Uint64(v) if lossless => i128::from(v),
+			_ => {
+				return Err(DeserializeError::Unexpected(
+					"f64",
+					xpc_message_to_type(message),
+				))
+			}
+		};
+		if is_exact(value) {
+			Ok(value as f64)
+		} else {
+			Err(DeserializeError::OutOfRange { expected, value })
+		}
+	}
+
+	pub(crate) fn char(&self, message: &Message) -> Result<char, DeserializeError> {
+		match *message {
+			Message::Uint64(v) => char::from_u32(narrow("char", v)?),
+			Message::Int64(v) => char::from_u32(narrow("char", v)?),
+			Message::String(ref s) => {
+				let mut chars = s.to_str()?.chars();
+				match (chars.next(), chars.next()) {
+					(Some(c), None) => Some(c),
+					_ => None,
+				}
+			}
+			_ => {
+				return Err(DeserializeError::Unexpected(
+					"u64",
+					xpc_message_to_type(message),
+				))
+			}
+		}
+		.ok_or(DeserializeError::Unexpected(
+			"utf-8 character",
+			xpc_message_to_type(message),
+		))
+	}
+}
+
+/// A message being deserialized, either owned or borrowed.
+///
+/// Owned messages hand their strings and bytes to the visitor by value, and
+/// borrowed messages lend them out for `'de`, so one [XpcDeserializer] serves
+/// both [deserialize](crate::deserialize) and
+/// [from_message_ref](crate::from_message_ref).
+pub(crate) trait Input<'de>: Sized {
+	type String: Text<'de>;
+	type Bytes: Bytes<'de>;
+	type Elements: ExactSizeIterator<Item = Self>;
+	type Entries: ExactSizeIterator<Item = (Self::String, Self)>;
+
+	fn message(&self) -> &Message;
+
+	/// Splits off the contents that are moved or borrowed out of the message.
+	fn unpack(self) -> Unpacked<'de, Self>;
+}
+
+/// The contents of an [Input] that aren't simply copied out of it.
+pub(crate) enum Unpacked<'de, I: Input<'de>> {
+	String(I::String),
+	Data(I::Bytes),
+	Uuid(I::Bytes),
+	Array(I::Elements),
+	Dictionary(I::Entries),
+	/// Any other message, read through [Input::message].
+	Other(I),
+}
+
+/// A string taken from an [Input].
+pub(crate) trait Text<'de> {
+	fn c_str(&self) -> &CStr;
+
+	/// Visits the string without moving it, borrowing it from the message if
+	/// the message is borrowed.
+	fn visit_ref<V>(&self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>;
+
+	/// Visits the string, moving it out of the message if the message is owned.
+	fn visit<V>(self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>;
+}
+
+/// The bytes of a [Message::Data] or [Message::Uuid] taken from an [Input].
+pub(crate) trait Bytes<'de> {
+	fn visit<V>(self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>;
+}
+
+impl<'de> Input<'de> for Message {
+	type String = CString;
+	type Bytes = Vec<u8>;
+	type Elements = vec::IntoIter<Message>;
+	type Entries = hash_map::IntoIter<CString, Message>;
+
+	fn message(&self) -> &Message {
+		self
+	}
+
+	fn unpack(self) -> Unpacked<'de, Self> {
+		match self {
+			Message::String(v) => Unpacked::String(v),
+			Message::Data(v) => Unpacked::Data(v),
+			Message::Uuid(v) => Unpacked::Uuid(v),
+			Message::Array(v) => Unpacked::Array(v.into_iter()),
+			Message::Dictionary(v) => Unpacked::Dictionary(v.into_iter()),
+			message => Unpacked::Other(message),
+		}
+	}
 }
 
-impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
+impl<'de> Input<'de> for &'de Message {
+	type String = &'de CString;
+	type Bytes = &'de [u8];
+	type Elements = slice::Iter<'de, Message>;
+	type Entries = hash_map::Iter<'de, CString, Message>;
+
+	fn message(&self) -> &Message {
+		self
+	}
+
+	fn unpack(self) -> Unpacked<'de, Self> {
+		match self {
+			Message::String(v) => Unpacked::String(v),
+			Message::Data(v) => Unpacked::Data(v.as_slice()),
+			Message::Uuid(v) => Unpacked::Uuid(v.as_slice()),
+			Message::Array(v) => Unpacked::Array(v.iter()),
+			Message::Dictionary(v) => Unpacked::Dictionary(v.iter()),
+			message => Unpacked::Other(message),
+		}
+	}
+}
+
+impl<'de> Text<'de> for CString {
+	fn c_str(&self) -> &CStr {
+		self
+	}
+
+	fn visit_ref<V>(&self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_str(self.to_str()?)
+	}
+
+	fn visit<V>(self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_string(self.into_string()?)
+	}
+}
+
+impl<'de> Text<'de> for &'de CString {
+	fn c_str(&self) -> &CStr {
+		self
+	}
+
+	fn visit_ref<V>(&self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>,
+	{
+		(*self).visit(visitor)
+	}
+
+	fn visit<V>(self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_borrowed_str(self.to_str()?)
+	}
+}
+
+impl<'de> Bytes<'de> for Vec<u8> {
+	fn visit<V>(self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_byte_buf(self)
+	}
+}
+
+impl<'de> Bytes<'de> for &'de [u8] {
+	fn visit<V>(self, visitor: V) -> Result<V::Value, DeserializeError>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_borrowed_bytes(self)
+	}
+}
+
+pub(crate) struct XpcDeserializer<I> {
+	pub(crate) input: I,
+	pub(crate) config: DeserializerConfig,
+}
+
+impl<I> XpcDeserializer<I> {
+	fn kind<'de>(&self) -> &'static str
+	where
+		I: Input<'de>,
+	{
+		xpc_message_to_type(self.input.message())
+	}
+}
+
+impl<'de, I> Deserializer<'de> for XpcDeserializer<I>
+where
+	I: Input<'de>,
+{
 	type Error = DeserializeError;
 
 	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Bool(v) => visitor.visit_bool(v),
-			Message::Double(v) => visitor.visit_f64(v),
-			Message::Int64(v) => visitor.visit_i64(v),
-			Message::String(v) => visitor.visit_string(v.into_string().unwrap()),
-			Message::Dictionary(v) => visitor.visit_map(MapAccessor {
-				elements: v.into_iter().collect(),
-				current_value: None,
+		match *self.input.message() {
+			Message::Bool(v) => return visitor.visit_bool(v),
+			Message::Double(v) => return visitor.visit_f64(v),
+			Message::Int64(v) => return visitor.visit_i64(v),
+			Message::Uint64(v) => return visitor.visit_u64(v),
+			Message::Null => return visitor.visit_unit(),
+			// Uuid deserializes from a newtype struct holding the bytes.
+			Message::Uuid(ref v) => {
+				return visitor.visit_newtype_struct(BytesDeserializer::<DeserializeError>::new(v))
+			}
+			_ => {}
+		}
+		let kind = self.kind();
+		match self.input.unpack() {
+			Unpacked::String(v) => v.visit(visitor),
+			Unpacked::Dictionary(v) => visitor.visit_map(MapAccessor::<I> {
+				entries: v,
+				current: None,
+				config: self.config,
 			}),
-			Message::Array(v) => visitor.visit_seq(VecAccessor { elements: v.into() }),
-			Message::Data(v) => visitor.visit_byte_buf(v),
-			Message::Uint64(v) => visitor.visit_u64(v),
-			Message::Null => visitor.visit_unit(),
-			_ => Err(DeserializeError::Unexpected(
-				"any valid type",
-				xpc_message_to_type(&self.message),
-			)),
+			Unpacked::Array(v) => visitor.visit_seq(SeqAccessor {
+				elements: v.enumerate(),
+				config: self.config,
+			}),
+			Unpacked::Data(v) => v.visit(visitor),
+			_ => Err(DeserializeError::Unexpected("any valid type", kind)),
 		}
 	}
 
@@ -52,12 +485,9 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
+		match *self.input.message() {
 			Message::Bool(v) => visitor.visit_bool(v),
-			_ => Err(DeserializeError::Unexpected(
-				"bool",
-				xpc_message_to_type(&self.message),
-			)),
+			_ => Err(DeserializeError::Unexpected("bool", self.kind())),
 		}
 	}
 
@@ -65,173 +495,113 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Int64(v) => visitor.visit_i8(v as i8),
-			_ => Err(DeserializeError::Unexpected(
-				"i64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_i8(self.config.integer(self.input.message(), "i8", true)?)
 	}
 
 	fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Int64(v) => visitor.visit_i16(v as i16),
-			_ => Err(DeserializeError::Unexpected(
-				"i64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_i16(self.config.integer(self.input.message(), "i16", true)?)
 	}
 
 	fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Int64(v) => visitor.visit_i32(v as i32),
-			Message::Uint64(v) => visitor.visit_i32(v as i32),
-			_ => Err(DeserializeError::Unexpected(
-				"i64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_i32(self.config.integer(self.input.message(), "i32", true)?)
 	}
 
 	fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Int64(v) => visitor.visit_i64(v),
-			_ => Err(DeserializeError::Unexpected(
-				"i64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_i64(self.config.integer(self.input.message(), "i64", true)?)
 	}
 
 	fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Uint64(v) => visitor.visit_u8(v as u8),
-			_ => Err(DeserializeError::Unexpected(
-				"u64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_u8(self.config.integer(self.input.message(), "u8", false)?)
 	}
 
 	fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Uint64(v) => visitor.visit_u16(v as u16),
-			_ => Err(DeserializeError::Unexpected(
-				"u64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_u16(self.config.integer(self.input.message(), "u16", false)?)
 	}
 
 	fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Int64(v) => visitor.visit_u32(v as u32),
-			Message::Uint64(v) => visitor.visit_u32(v as u32),
-			_ => Err(DeserializeError::Unexpected(
-				"u64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_u32(self.config.integer(self.input.message(), "u32", false)?)
 	}
 
 	fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Uint64(v) => visitor.visit_u64(v),
-			_ => Err(DeserializeError::Unexpected(
-				"u64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_u64(self.config.integer(self.input.message(), "u64", false)?)
+	}
+
+	fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_i128(
+			self.config
+				.wide(self.input.message(), "i128", |bits| bits as i128)?,
+		)
+	}
+
+	fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_u128(
+			self.config
+				.wide(self.input.message(), "u128", |bits| bits)?,
+		)
 	}
 
 	fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Double(v) => visitor.visit_f32(v as f32),
-			_ => Err(DeserializeError::Unexpected(
-				"f64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_f32(
+			self.config
+				.float(self.input.message(), "f32", |v| (v as f32) as i128 == v)? as f32,
+		)
 	}
 
 	fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Double(v) => visitor.visit_f64(v),
-			_ => Err(DeserializeError::Unexpected(
-				"f64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_f64(
+			self.config
+				.float(self.input.message(), "f64", |v| (v as f64) as i128 == v)?,
+		)
 	}
 
 	fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Uint64(v) => match char::from_u32(v as u32) {
-				Some(c) => visitor.visit_char(c),
-				None => Err(DeserializeError::Unexpected(
-					"utf-8 character",
-					xpc_message_to_type(&self.message),
-				)),
-			},
-			Message::Int64(v) => match char::from_u32(v as u32) {
-				Some(c) => visitor.visit_char(c),
-				None => Err(DeserializeError::Unexpected(
-					"utf-8 character",
-					xpc_message_to_type(&self.message),
-				)),
-			},
-			Message::String(s) if s.to_str().map(str::len).unwrap_or(0) == 1 => {
-				visitor.visit_char(s.to_str()?.chars().next().unwrap())
-			}
-			_ => Err(DeserializeError::Unexpected(
-				"u64",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		visitor.visit_char(self.config.char(self.input.message())?)
 	}
 
 	fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::String(s) => visitor.visit_str(s.to_str()?),
-			_ => Err(DeserializeError::Unexpected(
-				"string",
-				xpc_message_to_type(&self.message),
-			)),
+		let kind = self.kind();
+		match self.input.unpack() {
+			Unpacked::String(s) => s.visit(visitor),
+			_ => Err(DeserializeError::Unexpected("string", kind)),
 		}
 	}
 
@@ -239,25 +609,17 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::String(s) => visitor.visit_string(s.into_string()?),
-			_ => Err(DeserializeError::Unexpected(
-				"string",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		self.deserialize_str(visitor)
 	}
 
 	fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Data(s) => visitor.visit_bytes(s.as_ref()),
-			_ => Err(DeserializeError::Unexpected(
-				"bytes",
-				xpc_message_to_type(&self.message),
-			)),
+		let kind = self.kind();
+		match self.input.unpack() {
+			Unpacked::Data(v) => v.visit(visitor),
+			_ => Err(DeserializeError::Unexpected("bytes", kind)),
 		}
 	}
 
@@ -265,20 +627,14 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Data(s) => visitor.visit_byte_buf(s),
-			_ => Err(DeserializeError::Unexpected(
-				"bytes",
-				xpc_message_to_type(&self.message),
-			)),
-		}
+		self.deserialize_bytes(visitor)
 	}
 
 	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
+		match self.input.message() {
 			Message::Null => visitor.visit_none(),
 			_ => visitor.visit_some(self),
 		}
@@ -288,12 +644,9 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
+		match self.input.message() {
 			Message::Null => visitor.visit_unit(),
-			_ => Err(DeserializeError::Unexpected(
-				"null",
-				xpc_message_to_type(&self.message),
-			)),
+			_ => Err(DeserializeError::Unexpected("null", self.kind())),
 		}
 	}
 
@@ -310,12 +663,32 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 
 	fn deserialize_newtype_struct<V>(
 		self,
-		_name: &'static str,
+		name: &'static str,
 		visitor: V,
 	) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
+		if name == DATE_NAME {
+			return match *self.input.message() {
+				Message::Int64(v) => visitor.visit_newtype_struct(v.into_deserializer()),
+				Message::Uint64(v) if self.config.numeric_coercion == NumericCoercion::Lossless => {
+					let v: i64 = narrow("date", v)?;
+					visitor.visit_newtype_struct(v.into_deserializer())
+				}
+				_ => Err(DeserializeError::Unexpected("date", self.kind())),
+			};
+		}
+		if name == UUID_NAME {
+			let lenient = self.config.uuid_coercion == UuidCoercion::Lenient;
+			let kind = self.kind();
+			return match self.input.unpack() {
+				Unpacked::Uuid(v) => v.visit(visitor),
+				Unpacked::Data(v) if lenient => v.visit(visitor),
+				Unpacked::String(v) if lenient => v.visit(visitor),
+				_ => Err(DeserializeError::Unexpected("uuid", kind)),
+			};
+		}
 		visitor.visit_newtype_struct(self)
 	}
 
@@ -323,17 +696,13 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Array(array) => {
-				let access = VecAccessor {
-					elements: VecDeque::from(array),
-				};
-				visitor.visit_seq(access)
-			}
-			_ => Err(DeserializeError::Unexpected(
-				"array",
-				xpc_message_to_type(&self.message),
-			)),
+		let kind = self.kind();
+		match self.input.unpack() {
+			Unpacked::Array(v) => visitor.visit_seq(SeqAccessor {
+				elements: v.enumerate(),
+				config: self.config,
+			}),
+			_ => Err(DeserializeError::Unexpected("array", kind)),
 		}
 	}
 
@@ -360,18 +729,14 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::Dictionary(map) => {
-				let access = MapAccessor {
-					elements: map.into_iter().collect(),
-					current_value: None,
-				};
-				visitor.visit_map(access)
-			}
-			_ => Err(DeserializeError::Unexpected(
-				"map",
-				xpc_message_to_type(&self.message),
-			)),
+		let kind = self.kind();
+		match self.input.unpack() {
+			Unpacked::Dictionary(v) => visitor.visit_map(MapAccessor::<I> {
+				entries: v,
+				current: None,
+				config: self.config,
+			}),
+			_ => Err(DeserializeError::Unexpected("map", kind)),
 		}
 	}
 
@@ -389,28 +754,73 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 
 	fn deserialize_enum<V>(
 		self,
-		_name: &'static str,
+		name: &'static str,
 		_variants: &'static [&'static str],
 		visitor: V,
 	) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self.message {
-			Message::String(s) => visitor.visit_enum(s.to_str()?.to_string().into_deserializer()),
-			Message::Dictionary(map) => visitor.visit_enum(EnumAccessor { map, variant: None }),
-			_ => Err(DeserializeError::Unexpected(
-				"enum",
-				xpc_message_to_type(&self.message),
-			)),
+		if let Some(variant) = peer_error_variant(name, self.input.message()) {
+			return visitor.visit_enum(variant.into_deserializer());
 		}
+		let kind = self.kind();
+		let (variant, value) = match (self.config.enum_representation, self.input.unpack()) {
+			(EnumRepresentation::ExternallyTagged, Unpacked::String(key)) => {
+				(Variant::Key(key), None)
+			}
+			(EnumRepresentation::Index, Unpacked::Other(message))
+				if matches!(message.message(), Message::Uint64(_) | Message::Int64(_)) =>
+			{
+				(Variant::Tag(message), None)
+			}
+			(EnumRepresentation::ExternallyTagged, Unpacked::Dictionary(mut entries)) => {
+				if entries.len() != 1 {
+					let mut keys = entries
+						.map(|(key, _)| key.c_str().to_string_lossy().into_owned())
+						.collect::<Vec<_>>();
+					keys.sort();
+					return Err(DeserializeError::AmbiguousEnum(keys));
+				}
+				let (key, value) = entries
+					.next()
+					.ok_or_else(|| DeserializeError::AmbiguousEnum(Vec::new()))?;
+				(Variant::Key(key), Some(value))
+			}
+			(_, Unpacked::Dictionary(entries)) => {
+				let (mut variant, mut value) = (None, None);
+				for (key, message) in entries {
+					match key.c_str().to_str()? {
+						ENUM_TYPE_KEY => variant = Some(message),
+						ENUM_VALUE_KEY => value = Some(message),
+						_ => {}
+					}
+				}
+				(
+					Variant::Tag(variant.ok_or(DeserializeError::MissingField(ENUM_TYPE_KEY))?),
+					value,
+				)
+			}
+			_ => return Err(DeserializeError::Unexpected("enum", kind)),
+		};
+		visitor.visit_enum(EnumAccessor {
+			variant,
+			value,
+			config: self.config,
+		})
 	}
 
 	fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		self.deserialize_str(visitor)
+		// Variant indices are read from either integer kind, whatever the
+		// numeric coercion, since C peers commonly send them as int64.
+		match *self.input.message() {
+			Message::Uint64(v) => visitor.visit_u64(v),
+			Message::Int64(v) => visitor.visit_u64(narrow("u64", v)?),
+			_ => self.deserialize_str(visitor),
+		}
 	}
 
 	fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
@@ -421,26 +831,31 @@ impl<'de, 'a> Deserializer<'de> for XpcDeserializer {
 	}
 }
 
-pub(crate) struct VecAccessor {
-	elements: VecDeque<Message>,
+pub(crate) struct SeqAccessor<E> {
+	elements: iter::Enumerate<E>,
+	config: DeserializerConfig,
 }
 
-impl<'de> SeqAccess<'de> for VecAccessor {
+impl<'de, E, I> SeqAccess<'de> for SeqAccessor<E>
+where
+	E: ExactSizeIterator<Item = I>,
+	I: Input<'de>,
+{
 	type Error = DeserializeError;
 
 	fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
 	where
 		T: DeserializeSeed<'de>,
 	{
-		if self.elements.is_empty() {
-			Ok(None)
-		} else {
-			let message = self
-				.elements
-				.pop_front()
-				.ok_or(DeserializeError::EndOfArray)?;
-			seed.deserialize(XpcDeserializer { message })
+		match self.elements.next() {
+			Some((index, input)) => seed
+				.deserialize(XpcDeserializer {
+					input,
+					config: self.config,
+				})
 				.map(Option::Some)
+				.map_err(|error| error.at(PathSegment::Index(index))),
+			None => Ok(None),
 		}
 	}
 
@@ -449,30 +864,32 @@ impl<'de> SeqAccess<'de> for VecAccessor {
 	}
 }
 
-pub(crate) struct MapAccessor {
-	elements: VecDeque<(CString, Message)>,
-	current_value: Option<Message>,
+pub(crate) struct MapAccessor<'de, I: Input<'de>> {
+	entries: I::Entries,
+	current: Option<(I::String, I)>,
+	config: DeserializerConfig,
 }
 
-impl<'de> MapAccess<'de> for MapAccessor {
+impl<'de, I> MapAccess<'de> for MapAccessor<'de, I>
+where
+	I: Input<'de>,
+{
 	type Error = DeserializeError;
 
 	fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
 	where
 		K: DeserializeSeed<'de>,
 	{
-		if self.elements.is_empty() {
-			Ok(None)
-		} else {
-			let (key, value) = self
-				.elements
-				.pop_front()
-				.ok_or(DeserializeError::EndOfArray)?;
-			self.current_value = Some(value);
-			seed.deserialize(XpcDeserializer {
-				message: Message::String(key),
-			})
-			.map(Option::Some)
+		match self.entries.next() {
+			Some((key, value)) => {
+				let result = seed
+					.deserialize(MapKeyDeserializer { key: &key })
+					.map(Option::Some)
+					.map_err(|error| error.at(PathSegment::key(key.c_str())));
+				self.current = Some((key, value));
+				result
+			}
+			None => Ok(None),
 		}
 	}
 
@@ -480,94 +897,235 @@ impl<'de> MapAccess<'de> for MapAccessor {
 	where
 		V: DeserializeSeed<'de>,
 	{
-		match self.current_value.take() {
-			Some(message) => seed.deserialize(XpcDeserializer { message }),
-			None => panic!("value called without key"),
+		match self.current.take() {
+			Some((key, input)) => seed
+				.deserialize(XpcDeserializer {
+					input,
+					config: self.config,
+				})
+				.map_err(|error| error.at(PathSegment::key(key.c_str()))),
+			None => Err(DeserializeError::ValueWithoutKey),
 		}
 	}
 
 	fn size_hint(&self) -> Option<usize> {
-		Some(self.elements.len())
+		Some(self.entries.len())
 	}
 }
 
-pub(crate) struct EnumAccessor {
-	map: HashMap<CString, Message>,
-	variant: Option<CString>,
+/// Deserializes the keys of a [Message::Dictionary], parsing them back into
+/// integers, bools and chars when requested.
+pub(crate) struct MapKeyDeserializer<'a, K> {
+	key: &'a K,
 }
 
-impl<'de> EnumAccess<'de> for EnumAccessor {
+macro_rules! deserialize_parsed_key {
+	($($method:ident => $visit:ident($ty:ty)),* $(,)?) => {
+		$(
+			fn $method<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+			where
+				V: de::Visitor<'de>,
+			{
+				match self.key.c_str().to_str()?.parse::<$ty>() {
+					Ok(v) => visitor.$visit(v),
+					Err(_) => Err(DeserializeError::InvalidKey(stringify!($ty))),
+				}
+			}
+		)*
+	};
+}
+
+impl<'de, 'a, K> Deserializer<'de> for MapKeyDeserializer<'a, K>
+where
+	K: Text<'de>,
+{
 	type Error = DeserializeError;
-	type Variant = Self;
 
-	fn variant_seed<V>(mut self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+	deserialize_parsed_key! {
+		deserialize_bool => visit_bool(bool),
+		deserialize_i8 => visit_i8(i8),
+		deserialize_i16 => visit_i16(i16),
+		deserialize_i32 => visit_i32(i32),
+		deserialize_i64 => visit_i64(i64),
+		deserialize_u8 => visit_u8(u8),
+		deserialize_u16 => visit_u16(u16),
+		deserialize_u32 => visit_u32(u32),
+		deserialize_u64 => visit_u64(u64),
+		deserialize_i128 => visit_i128(i128),
+		deserialize_u128 => visit_u128(u128),
+		deserialize_char => visit_char(char),
+	}
+
+	fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		self.key.visit_ref(visitor)
+	}
+
+	fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_some(self)
+	}
+
+	fn deserialize_newtype_struct<V>(
+		self,
+		_name: &'static str,
+		visitor: V,
+	) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_newtype_struct(self)
+	}
+
+	fn deserialize_enum<V>(
+		self,
+		_name: &'static str,
+		_variants: &'static [&'static str],
+		visitor: V,
+	) -> Result<V::Value, Self::Error>
+	where
+		V: de::Visitor<'de>,
+	{
+		visitor.visit_enum(self.key.c_str().to_str()?.into_deserializer())
+	}
+
+	serde::forward_to_deserialize_any! {
+		f32 f64 str string bytes byte_buf unit unit_struct seq tuple tuple_struct
+		map struct identifier ignored_any
+	}
+}
+
+/// Where the variant of an enum comes from: either a dictionary key or a
+/// standalone message.
+enum Variant<'de, I: Input<'de>> {
+	Key(I::String),
+	Tag(I),
+}
+
+pub(crate) struct EnumAccessor<'de, I: Input<'de>> {
+	variant: Variant<'de, I>,
+	value: Option<I>,
+	config: DeserializerConfig,
+}
+
+impl<'de, I> EnumAccess<'de> for EnumAccessor<'de, I>
+where
+	I: Input<'de>,
+{
+	type Error = DeserializeError;
+	type Variant = VariantAccessor<'de, I>;
+
+	fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
 	where
 		V: DeserializeSeed<'de>,
 	{
-		let key = self
-			.map
-			.keys()
-			.cloned()
-			.next()
-			.ok_or(DeserializeError::EndOfArray)?;
-		self.variant = Some(key.clone());
-		let key = seed.deserialize(XpcDeserializer {
-			message: Message::String(key),
-		})?;
-		Ok((key, self))
+		let (variant, key) = match self.variant {
+			Variant::Key(key) => (
+				seed.deserialize(MapKeyDeserializer { key: &key })?,
+				Some(key),
+			),
+			Variant::Tag(input) => (
+				seed.deserialize(XpcDeserializer {
+					input,
+					config: self.config,
+				})?,
+				None,
+			),
+		};
+		Ok((
+			variant,
+			VariantAccessor {
+				value: self.value,
+				key,
+				config: self.config,
+			},
+		))
+	}
+}
+
+pub(crate) struct VariantAccessor<'de, I: Input<'de>> {
+	value: Option<I>,
+	/// The dictionary key the value sits under, when it isn't [ENUM_VALUE_KEY].
+	key: Option<I::String>,
+	config: DeserializerConfig,
+}
+
+impl<'de, I> VariantAccessor<'de, I>
+where
+	I: Input<'de>,
+{
+	fn deserialize_value<T, F>(self, f: F) -> Result<T, DeserializeError>
+	where
+		F: FnOnce(XpcDeserializer<I>) -> Result<T, DeserializeError>,
+	{
+		let input = self
+			.value
+			.ok_or(DeserializeError::MissingField(ENUM_VALUE_KEY))?;
+		let key = self.key;
+		f(XpcDeserializer {
+			input,
+			config: self.config,
+		})
+		.map_err(|error| {
+			error.at(match key {
+				Some(key) => PathSegment::key(key.c_str()),
+				None => PathSegment::Key(ENUM_VALUE_KEY.to_owned()),
+			})
+		})
 	}
 }
 
-impl<'de> VariantAccess<'de> for EnumAccessor {
+impl<'de, I> VariantAccess<'de> for VariantAccessor<'de, I>
+where
+	I: Input<'de>,
+{
 	type Error = DeserializeError;
 
 	fn unit_variant(self) -> Result<(), Self::Error> {
-		Err(DeserializeError::Unexpected("string", "map"))
+		match self.value.as_ref().map(Input::message) {
+			None | Some(Message::Null) => Ok(()),
+			Some(_) => self
+				.deserialize_value(|value| Err(DeserializeError::Unexpected("null", value.kind()))),
+		}
 	}
 
-	fn newtype_variant_seed<T>(mut self, seed: T) -> Result<T::Value, Self::Error>
+	fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
 	where
 		T: DeserializeSeed<'de>,
 	{
-		match self
-			.variant
-			.take()
-			.and_then(|variant| self.map.remove(&variant))
-		{
-			Some(message) => seed.deserialize(XpcDeserializer { message }),
-			None => Err(DeserializeError::EndOfArray),
-		}
+		self.deserialize_value(|value| seed.deserialize(value))
 	}
 
-	fn tuple_variant<V>(mut self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+	fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self
-			.variant
-			.take()
-			.and_then(|variant| self.map.remove(&variant))
-		{
-			Some(message) => XpcDeserializer { message }.deserialize_seq(visitor),
-			None => Err(DeserializeError::EndOfArray),
-		}
+		self.deserialize_value(|value| value.deserialize_seq(visitor))
 	}
 
 	fn struct_variant<V>(
-		mut self,
+		self,
 		_fields: &'static [&'static str],
 		visitor: V,
 	) -> Result<V::Value, Self::Error>
 	where
 		V: de::Visitor<'de>,
 	{
-		match self
-			.variant
-			.take()
-			.and_then(|variant| self.map.remove(&variant))
-		{
-			Some(message) => XpcDeserializer { message }.deserialize_map(visitor),
-			None => Err(DeserializeError::EndOfArray),
+		self.deserialize_value(|value| value.deserialize_map(visitor))
+	}
+}
+
+/// The [XpcPeerError] variant a [Message::Error] deserializes into, if it is
+/// being deserialized as one.
+fn peer_error_variant(name: &'static str, message: &Message) -> Option<&'static str> {
+	match message {
+		Message::Error(error) if name == PEER_ERROR_NAME => {
+			Some(XpcPeerError::from(error).variant())
 		}
+		_ => None,
 	}
 }
@@ -0,0 +1,213 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::xpc_message_to_type;
+use std::{ffi::CStr, fmt};
+use xpc_connection::Message;
+
+/// Options controlling how a [Message] is described.
+#[derive(Debug, Clone, Copy)]
+pub struct DescriptionConfig {
+	indent: Option<&'static str>,
+	sort_keys: bool,
+	max_data_length: Option<usize>,
+	max_depth: Option<usize>,
+}
+
+impl Default for DescriptionConfig {
+	fn default() -> Self {
+		Self {
+			indent: Some("\t"),
+			sort_keys: true,
+			max_data_length: Some(64),
+			max_depth: None,
+		}
+	}
+}
+
+impl DescriptionConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets the string each nesting level is indented with, putting every
+	/// array element and dictionary entry on its own line, or [None] to
+	/// describe the whole message on one line. Defaults to a tab.
+	pub fn indent(mut self, indent: Option<&'static str>) -> Self {
+		self.indent = indent;
+		self
+	}
+
+	/// Sets whether dictionary entries are sorted by key, rather than printed
+	/// in hash map order. Defaults to `true`.
+	pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+		self.sort_keys = sort_keys;
+		self
+	}
+
+	/// Sets how many bytes of [Message::Data] are printed before the rest is
+	/// elided, or [None] to always print everything. Defaults to 64.
+	pub fn max_data_length(mut self, max_data_length: Option<usize>) -> Self {
+		self.max_data_length = max_data_length;
+		self
+	}
+
+	/// Sets how many levels of arrays and dictionaries are expanded before
+	/// their contents are elided, or [None] for no limit. Defaults to [None].
+	pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+		self.max_depth = max_depth;
+		self
+	}
+
+	/// Describes a message using this configuration.
+	pub fn describe<'a>(&self, message: &'a Message) -> Description<'a> {
+		Description {
+			message,
+			config: *self,
+		}
+	}
+}
+
+/// A human-readable description of a [Message], in the style of
+/// `xpc_copy_description`. Created by [describe](crate::describe) or
+/// [DescriptionConfig::describe].
+///
+/// ```text
+/// <dictionary> { count = 2, contents =
+///     "name" => <string> { length = 4, contents = "main" }
+///     "port" => <uint64: 80>
+/// }
+/// ```
+pub struct Description<'a> {
+	message: &'a Message,
+	config: DescriptionConfig,
+}
+
+/// What precedes an element of a container.
+enum Label<'a> {
+	Index(usize),
+	Key(&'a CStr),
+}
+
+impl<'a> Description<'a> {
+	fn write(&self, f: &mut fmt::Formatter<'_>, message: &Message, depth: usize) -> fmt::Result {
+		match message {
+			Message::Null => f.write_str("<null>"),
+			Message::Bool(v) => write!(f, "<bool: {}>", v),
+			Message::Int64(v) => write!(f, "<int64: {}>", v),
+			Message::Uint64(v) => write!(f, "<uint64: {}>", v),
+			Message::Double(v) => write!(f, "<double: {:?}>", v),
+			Message::String(v) => write!(
+				f,
+				"<string> {{ length = {}, contents = {:?} }}",
+				v.as_bytes().len(),
+				v.to_string_lossy()
+			),
+			Message::Data(v) => {
+				write!(f, "<data> {{ length = {} bytes, contents = 0x", v.len())?;
+				let shown = self
+					.config
+					.max_data_length
+					.map_or(v.len(), |max| max.min(v.len()));
+				for byte in &v[..shown] {
+					write!(f, "{:02x}", byte)?;
+				}
+				if shown < v.len() {
+					f.write_str("...")?;
+				}
+				f.write_str(" }")
+			}
+			Message::Uuid(v) => {
+				f.write_str("<uuid: ")?;
+				for (i, byte) in v.iter().enumerate() {
+					if v.len() == 16 && (i == 4 || i == 6 || i == 8 || i == 10) {
+						f.write_str("-")?;
+					}
+					write!(f, "{:02x}", byte)?;
+				}
+				f.write_str(">")
+			}
+			Message::Error(v) => write!(f, "<error: {:?}>", v),
+			Message::Array(v) => self.container(
+				f,
+				"array",
+				depth,
+				v.iter()
+					.enumerate()
+					.map(|(i, message)| (Label::Index(i), message))
+					.collect(),
+			),
+			Message::Dictionary(v) => {
+				let mut entries = v.iter().collect::<Vec<_>>();
+				if self.config.sort_keys {
+					entries.sort_by_key(|(key, _)| *key);
+				}
+				self.container(
+					f,
+					"dictionary",
+					depth,
+					entries
+						.into_iter()
+						.map(|(key, message)| (Label::Key(key), message))
+						.collect(),
+				)
+			}
+			_ => write!(f, "<{}>", xpc_message_to_type(message)),
+		}
+	}
+
+	fn container(
+		&self,
+		f: &mut fmt::Formatter<'_>,
+		kind: &str,
+		depth: usize,
+		entries: Vec<(Label<'_>, &Message)>,
+	) -> fmt::Result {
+		write!(f, "<{}> {{ count = {}", kind, entries.len())?;
+		if entries.is_empty() {
+			return f.write_str(" }");
+		}
+		if matches!(self.config.max_depth, Some(max) if depth >= max) {
+			return f.write_str(", contents = ... }");
+		}
+		f.write_str(", contents =")?;
+		for (i, (label, message)) in entries.into_iter().enumerate() {
+			match self.config.indent {
+				Some(indent) => {
+					f.write_str("\n")?;
+					for _ in 0..=depth {
+						f.write_str(indent)?;
+					}
+				}
+				None if i == 0 => f.write_str(" ")?,
+				None => f.write_str(", ")?,
+			}
+			match label {
+				Label::Index(i) => write!(f, "{}: ", i)?,
+				Label::Key(key) => write!(f, "{:?} => ", key.to_string_lossy())?,
+			}
+			self.write(f, message, depth + 1)?;
+		}
+		match self.config.indent {
+			Some(indent) => {
+				f.write_str("\n")?;
+				for _ in 0..depth {
+					f.write_str(indent)?;
+				}
+				f.write_str("}")
+			}
+			None => f.write_str(" }"),
+		}
+	}
+}
+
+impl<'a> fmt::Display for Description<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.write(f, self.message, 0)
+	}
+}
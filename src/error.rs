@@ -6,7 +6,52 @@
 	file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use serde::{de, ser};
+use serde::{de, ser, Deserialize, Serialize};
+use std::{ffi::CStr, fmt};
+use xpc_connection::MessageError;
+
+/// A single step from a container to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+	/// A dictionary key, or the variant key of an externally tagged enum.
+	Key(String),
+	/// An array index.
+	Index(usize),
+}
+
+impl PathSegment {
+	pub(crate) fn key(key: &CStr) -> Self {
+		Self::Key(key.to_string_lossy().into_owned())
+	}
+}
+
+/// The location of a value within a [Message](xpc_connection::Message) or the
+/// value it was serialized from, written as `.settings.sources[3].port`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path(Vec<PathSegment>);
+
+impl Path {
+	/// The segments leading from the root message to the value.
+	pub fn segments(&self) -> &[PathSegment] {
+		&self.0
+	}
+
+	fn push_front(&mut self, segment: PathSegment) {
+		self.0.insert(0, segment);
+	}
+}
+
+impl fmt::Display for Path {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for segment in &self.0 {
+			match segment {
+				PathSegment::Key(key) => write!(f, ".{}", key)?,
+				PathSegment::Index(index) => write!(f, "[{}]", index)?,
+			}
+		}
+		Ok(())
+	}
+}
 
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum SerializeError {
@@ -14,8 +59,52 @@ pub enum SerializeError {
 	NulString(#[from] std::ffi::NulError),
 	#[error("expected {0}")]
 	Expected(&'static str),
+	#[error("invalid map key: expected string, integer, bool, char or unit variant, got {0}")]
+	InvalidKey(&'static str),
+	#[error("serialize_value() called before serialize_key()")]
+	ValueWithoutKey,
+	#[error("struct variant serializer has no variant name")]
+	MissingVariant,
 	#[error("{0}")]
 	Custom(String),
+	#[error("{source} at {path}")]
+	WithPath {
+		path: Path,
+		source: Box<SerializeError>,
+	},
+}
+
+impl SerializeError {
+	/// Where in the value the error occurred, or [None] if it occurred at the
+	/// root.
+	pub fn path(&self) -> Option<&Path> {
+		match self {
+			Self::WithPath { path, .. } => Some(path),
+			_ => None,
+		}
+	}
+
+	/// The error itself, without the path it occurred at.
+	pub fn inner(&self) -> &SerializeError {
+		match self {
+			Self::WithPath { source, .. } => source,
+			_ => self,
+		}
+	}
+
+	/// Records that the error occurred below `segment`.
+	pub(crate) fn at(self, segment: PathSegment) -> Self {
+		match self {
+			Self::WithPath { mut path, source } => {
+				path.push_front(segment);
+				Self::WithPath { path, source }
+			}
+			error => Self::WithPath {
+				path: Path(vec![segment]),
+				source: Box::new(error),
+			},
+		}
+	}
 }
 
 impl ser::Error for SerializeError {
@@ -35,10 +124,340 @@ pub enum DeserializeError {
 	InvalidString(#[from] std::ffi::IntoStringError),
 	#[error("expected {0}, got {1}")]
 	Unexpected(&'static str, &'static str),
+	#[error("{value} is out of range for {expected}")]
+	OutOfRange { expected: &'static str, value: i128 },
+	#[error("expected length {expected}, got {got}")]
+	InvalidLength { expected: usize, got: usize },
+	#[error("failed to parse {0} from a decimal string")]
+	InvalidDecimal(&'static str),
+	#[error("failed to parse map key as {0}")]
+	InvalidKey(&'static str),
 	#[error("attempted to fetch element from end of array")]
 	EndOfArray,
+	#[error("next_value() called before next_key()")]
+	ValueWithoutKey,
+	#[error("missing field {0}")]
+	MissingField(&'static str),
+	#[error("expected exactly one enum variant key, found {0:?}")]
+	AmbiguousEnum(Vec<String>),
+	#[error("unknown variant `{variant}`, expected one of {expected:?}")]
+	UnknownVariant {
+		variant: String,
+		expected: &'static [&'static str],
+	},
 	#[error("{0}")]
 	Custom(String),
+	#[error("{source} at {path}")]
+	WithPath {
+		path: Path,
+		source: Box<DeserializeError>,
+	},
+}
+
+impl DeserializeError {
+	/// Where in the message the error occurred, or [None] if it occurred at
+	/// the root.
+	pub fn path(&self) -> Option<&Path> {
+		match self {
+			Self::WithPath { path, .. } => Some(path),
+			_ => None,
+		}
+	}
+
+	/// The error itself, without the path it occurred at.
+	pub fn inner(&self) -> &DeserializeError {
+		match self {
+			Self::WithPath { source, .. } => source,
+			_ => self,
+		}
+	}
+
+	/// Records that the error occurred below `segment`.
+	pub(crate) fn at(self, segment: PathSegment) -> Self {
+		match self {
+			Self::WithPath { mut path, source } => {
+				path.push_front(segment);
+				Self::WithPath { path, source }
+			}
+			error => Self::WithPath {
+				path: Path(vec![segment]),
+				source: Box::new(error),
+			},
+		}
+	}
+}
+
+/// The name [XpcPeerError] is (de)serialized with, so the deserializer can
+/// recognise it and accept a [Message::Error](xpc_connection::Message::Error).
+pub(crate) const PEER_ERROR_NAME: &str = "$xpc_serde::XpcPeerError";
+
+/// An error libxpc delivered on a connection in place of a message. Displays
+/// as libxpc's description of the error.
+///
+/// A [Message::Error](xpc_connection::Message::Error) deserializes into this
+/// type. To deserialize a reply that may be an error instead, use
+/// [deserialize_reply](crate::deserialize_reply):
+///
+/// ```
+/// use xpc_connection::{Message, MessageError};
+/// use xpc_serde::error::XpcPeerError;
+///
+/// let reply = xpc_serde::deserialize_reply::<u64>(Message::Uint64(1)).unwrap();
+/// assert_eq!(reply, Ok(1));
+///
+/// let message = Message::Error(MessageError::ConnectionInterrupted);
+/// let reply = xpc_serde::deserialize_reply::<u64>(message).unwrap();
+/// assert_eq!(reply, Err(XpcPeerError::ConnectionInterrupted));
+/// ```
+///
+/// [deserialize_reply](crate::deserialize_reply) replaces deserializing into
+/// `Result<T, XpcPeerError>`, which only reads results serialized by this
+/// crate, and not a bare [Message::Error](xpc_connection::Message::Error).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, thiserror::Error)]
+#[serde(rename = "$xpc_serde::XpcPeerError")]
+#[non_exhaustive]
+pub enum XpcPeerError {
+	/// The peer exited or crashed. The connection is still usable, and
+	/// libxpc will relaunch the peer when the next message is sent.
+	#[error("Connection interrupted")]
+	ConnectionInterrupted,
+	/// The peer can't be reached, or the connection was cancelled. The
+	/// connection can't be used again.
+	#[error("Connection invalid")]
+	ConnectionInvalid,
+	/// The peer is about to exit, and should finish any outstanding work.
+	/// The pinned xpc-connection never delivers this error, but it can still
+	/// be constructed and (de)serialized.
+	#[error("Termination imminent")]
+	TerminationImminent,
+}
+
+impl XpcPeerError {
+	/// The name of the variant, as serialized.
+	pub(crate) fn variant(&self) -> &'static str {
+		match self {
+			Self::ConnectionInterrupted => "ConnectionInterrupted",
+			Self::ConnectionInvalid => "ConnectionInvalid",
+			Self::TerminationImminent => "TerminationImminent",
+		}
+	}
+
+	/// libxpc's description of the error, as it is displayed.
+	///
+	/// xpc-connection doesn't pass on the error dictionary libxpc sends, so
+	/// this is libxpc's fixed description for each kind of error.
+	pub fn description(&self) -> &'static str {
+		match self {
+			Self::ConnectionInterrupted => "Connection interrupted",
+			Self::ConnectionInvalid => "Connection invalid",
+			Self::TerminationImminent => "Termination imminent",
+		}
+	}
+}
+
+impl From<&MessageError> for XpcPeerError {
+	fn from(error: &MessageError) -> Self {
+		match error {
+			MessageError::ConnectionInterrupted => Self::ConnectionInterrupted,
+			MessageError::ConnectionInvalid => Self::ConnectionInvalid,
+		}
+	}
+}
+
+impl From<MessageError> for XpcPeerError {
+	fn from(error: MessageError) -> Self {
+		Self::from(&error)
+	}
+}
+
+/// Errors making or answering a call with [rpc](crate::rpc).
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum RpcError {
+	#[error(transparent)]
+	Serialize(#[from] SerializeError),
+	#[error(transparent)]
+	Deserialize(#[from] DeserializeError),
+	#[error(transparent)]
+	Peer(#[from] XpcPeerError),
+	#[error("requests and replies must be dictionaries, not {0}")]
+	NotADictionary(&'static str),
+	#[error("message has no request id")]
+	MissingId,
+	/// A request or reply has an id under [ID_KEY](crate::rpc::ID_KEY) that
+	/// isn't a non-negative integer.
+	#[error("request ids must be non-negative integers, not {0}")]
+	InvalidId(&'static str),
+	/// A request or reply has a field with the same name as one the rpc
+	/// protocol adds, such as [ID_KEY](crate::rpc::ID_KEY).
+	#[error("requests and replies can't have a field named {0}")]
+	ReservedKey(&'static str),
+	/// A [service](crate::xpc_service) replied with the result of a different
+	/// method than the one called.
+	#[error("reply is for a different method than the request")]
+	UnexpectedReply,
+	/// A [service](crate::xpc_service) was called with a method it doesn't
+	/// have.
+	#[error("unknown method {0}")]
+	UnknownMethod(String),
+	/// The server couldn't handle the request, and replied with a description
+	/// of why.
+	#[error("server failed to handle the request: {0}")]
+	Remote(String),
+	#[error("connection closed")]
+	Closed,
+}
+
+/// Errors converting a [Message](xpc_connection::Message) into a
+/// [Value](crate::Value).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValueError {
+	#[error("{0} messages can't be represented as a Value")]
+	Unsupported(&'static str),
+	#[error("uuid messages must be 16 bytes long, not {0}")]
+	InvalidUuid(usize),
+}
+
+/// A [SystemTime](std::time::SystemTime) too far from the Unix epoch to be
+/// converted into a [Date](crate::Date).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("date is too far from the Unix epoch to fit in nanoseconds")]
+pub struct DateOutOfRange;
+
+/// A string or byte slice that isn't a valid [Uuid](crate::Uuid).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+#[error("invalid UUID")]
+pub struct InvalidUuid;
+
+/// Errors converting between a [Message](xpc_connection::Message) and JSON.
+#[cfg(feature = "json")]
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum JsonError {
+	#[error("{0} messages can't be represented as JSON")]
+	Unsupported(&'static str),
+	#[error("JSON strings must be valid UTF-8: {0}")]
+	InvalidStr(#[from] std::str::Utf8Error),
+	#[error("failed to convert JSON string: {0}")]
+	NulString(#[from] std::ffi::NulError),
+	#[error("invalid or unknown tag {0}")]
+	InvalidTag(String),
+	#[error("number {0} can't be represented as a message")]
+	InvalidNumber(String),
+	#[error("invalid base64 data: {0}")]
+	InvalidBase64(#[from] base64::DecodeError),
+	#[error("uuids must be 16 bytes, not {0}")]
+	InvalidUuid(usize),
+}
+
+/// Errors converting between a [Message](xpc_connection::Message) and a
+/// property list, or reading and writing plist files.
+#[cfg(feature = "plist")]
+#[derive(Debug, thiserror::Error)]
+pub enum PlistError {
+	#[error("{0} values can't be converted between messages and plists")]
+	Unsupported(&'static str),
+	#[error("plist strings must be valid UTF-8: {0}")]
+	InvalidStr(#[from] std::str::Utf8Error),
+	#[error("failed to convert plist string: {0}")]
+	NulString(#[from] std::ffi::NulError),
+	#[error("uuids must be 16 bytes, not {0}")]
+	InvalidUuid(usize),
+	#[error(transparent)]
+	DateOutOfRange(#[from] DateOutOfRange),
+	#[error(transparent)]
+	Serialize(#[from] SerializeError),
+	#[error(transparent)]
+	Deserialize(#[from] DeserializeError),
+	#[error(transparent)]
+	Plist(#[from] plist::Error),
+}
+
+/// Errors encoding or decoding the [wire format](crate::wire). Offsets are in
+/// bytes from the start of the input.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum WireError {
+	#[error("{0} messages can't be encoded in the wire format")]
+	Unsupported(&'static str),
+	#[error("uuids must be 16 bytes, not {0}")]
+	InvalidUuid(usize),
+	#[error("{0} is too large for the wire format")]
+	TooLarge(&'static str),
+	#[error("invalid magic number {0:#010x}")]
+	InvalidMagic(u32),
+	#[error("unsupported wire format version {0}")]
+	UnsupportedVersion(u32),
+	#[error("unexpected end of input at offset {0}")]
+	UnexpectedEof(usize),
+	#[error("unknown object type {kind:#x} at offset {offset}")]
+	UnknownType { kind: u32, offset: usize },
+	#[error("{kind} object at offset {offset} can't be decoded")]
+	UnsupportedType { kind: &'static str, offset: usize },
+	#[error("invalid string at offset {0}")]
+	InvalidString(usize),
+	#[error("length of the container at offset {0} doesn't match its contents")]
+	InvalidLength(usize),
+	#[error("duplicate dictionary key at offset {0}")]
+	DuplicateKey(usize),
+	#[error("objects are nested too deeply at offset {0}")]
+	RecursionLimitExceeded(usize),
+	#[error("trailing bytes at offset {0}")]
+	TrailingBytes(usize),
+	#[error(transparent)]
+	Serialize(#[from] SerializeError),
+	#[error(transparent)]
+	Deserialize(#[from] DeserializeError),
+}
+
+/// An error parsing the [text notation](crate::text), with the position it
+/// was found at.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("{kind} at line {line}, column {column}")]
+pub struct ParseError {
+	line: usize,
+	column: usize,
+	kind: ParseErrorKind,
+}
+
+impl ParseError {
+	pub(crate) fn new(line: usize, column: usize, kind: ParseErrorKind) -> Self {
+		Self { line, column, kind }
+	}
+
+	/// The line the error was found on, starting from 1.
+	pub fn line(&self) -> usize {
+		self.line
+	}
+
+	/// The column the error was found at, in characters, starting from 1.
+	pub fn column(&self) -> usize {
+		self.column
+	}
+
+	pub fn kind(&self) -> &ParseErrorKind {
+		&self.kind
+	}
+}
+
+/// What went wrong parsing the [text notation](crate::text).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ParseErrorKind {
+	#[error("unexpected end of input")]
+	UnexpectedEof,
+	#[error("unexpected character {0:?}")]
+	UnexpectedChar(char),
+	#[error("trailing characters")]
+	TrailingCharacters,
+	#[error("invalid value {0:?}")]
+	InvalidWord(String),
+	#[error("invalid escape sequence")]
+	InvalidEscape,
+	#[error("strings can't contain NUL bytes")]
+	NulInString,
+	#[error("data must be an even number of hex digits")]
+	InvalidData,
+	#[error("duplicate key {0:?}")]
+	DuplicateKey(String),
+	#[error("arrays and dictionaries are nested too deeply")]
+	RecursionLimitExceeded,
 }
 
 impl de::Error for DeserializeError {
@@ -48,4 +467,11 @@ impl de::Error for DeserializeError {
 	{
 		Self::Custom(msg.to_string())
 	}
+
+	fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+		Self::UnknownVariant {
+			variant: variant.to_string(),
+			expected,
+		}
+	}
 }
@@ -0,0 +1,149 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Lossless conversion between [Message] and [serde_json::Value].
+//!
+//! JSON can't tell signed from unsigned integers and has no byte strings or
+//! non-finite numbers, so those are written as single-key objects whose key
+//! starts with `$`:
+//!
+//! | [Message]                      | JSON                              |
+//! |--------------------------------|-----------------------------------|
+//! | [Message::Null]                | `null`                            |
+//! | [Message::Bool]                | `true` / `false`                  |
+//! | [Message::Int64]               | `-1`                              |
+//! | [Message::Uint64]              | `{"$uint64": 1}`                  |
+//! | [Message::Double], finite      | `1.0`                             |
+//! | [Message::Double], non-finite  | `{"$double": "NaN"}`, `"Infinity"` or `"-Infinity"` |
+//! | [Message::String]              | `"text"`                          |
+//! | [Message::Data]                | `{"$data": "AQID"}` (standard base64, padded) |
+//! | [Message::Uuid]                | `{"$uuid": "00010203-0405-0607-0809-0a0b0c0d0e0f"}` |
+//! | [Message::Array]               | `[...]`                           |
+//! | [Message::Dictionary]          | `{...}`                           |
+//!
+//! A dictionary that itself has exactly one key starting with `$` is wrapped
+//! as `{"$dictionary": {...}}` so it isn't mistaken for a tag.
+//!
+//! When reading JSON that wasn't produced by [to_json], a plain integer too
+//! large for an [i64] becomes a [Message::Uint64]. Any other single-key object
+//! whose key starts with `$` is rejected.
+//!
+//! Strings and dictionary keys must be valid UTF-8, and only the message kinds
+//! listed above can be converted.
+
+use crate::{error::JsonError, xpc_message_to_type, Uuid};
+use serde_json::{Map, Number, Value};
+use std::{collections::HashMap, convert::TryFrom, ffi::CString};
+use xpc_connection::Message;
+
+const UINT64_TAG: &str = "$uint64";
+const DOUBLE_TAG: &str = "$double";
+const DATA_TAG: &str = "$data";
+const UUID_TAG: &str = "$uuid";
+const DICTIONARY_TAG: &str = "$dictionary";
+
+/// Converts a [Message] into JSON, following the convention described in the
+/// [module documentation](self).
+pub fn to_json(message: &Message) -> Result<Value, JsonError> {
+	Ok(match message {
+		Message::Null => Value::Null,
+		Message::Bool(v) => Value::Bool(*v),
+		Message::Int64(v) => Value::from(*v),
+		Message::Uint64(v) => tagged(UINT64_TAG, Value::from(*v)),
+		Message::Double(v) => match Number::from_f64(*v) {
+			Some(number) => Value::Number(number),
+			None if v.is_nan() => tagged(DOUBLE_TAG, Value::from("NaN")),
+			None if *v > 0.0 => tagged(DOUBLE_TAG, Value::from("Infinity")),
+			None => tagged(DOUBLE_TAG, Value::from("-Infinity")),
+		},
+		Message::String(v) => Value::String(v.to_str()?.to_owned()),
+		Message::Data(v) => tagged(DATA_TAG, Value::String(base64::encode(v))),
+		Message::Uuid(v) => {
+			let uuid = Uuid::try_from(v.as_slice()).map_err(|_| JsonError::InvalidUuid(v.len()))?;
+			tagged(UUID_TAG, Value::String(uuid.to_string()))
+		}
+		Message::Array(v) => Value::Array(v.iter().map(to_json).collect::<Result<_, _>>()?),
+		Message::Dictionary(v) => {
+			let map = v
+				.iter()
+				.map(|(key, value)| Ok((key.to_str()?.to_owned(), to_json(value)?)))
+				.collect::<Result<Map<_, _>, JsonError>>()?;
+			if map.len() == 1 && map.keys().all(|key| key.starts_with('$')) {
+				tagged(DICTIONARY_TAG, Value::Object(map))
+			} else {
+				Value::Object(map)
+			}
+		}
+		_ => return Err(JsonError::Unsupported(xpc_message_to_type(message))),
+	})
+}
+
+/// Converts JSON into a [Message], following the convention described in the
+/// [module documentation](self).
+pub fn from_json(value: &Value) -> Result<Message, JsonError> {
+	Ok(match value {
+		Value::Null => Message::Null,
+		Value::Bool(v) => Message::Bool(*v),
+		Value::Number(v) => number(v)?,
+		Value::String(v) => Message::String(CString::new(v.as_str())?),
+		Value::Array(v) => Message::Array(v.iter().map(from_json).collect::<Result<_, _>>()?),
+		Value::Object(v) => match v.iter().next() {
+			Some((tag, value)) if v.len() == 1 && tag.starts_with('$') => untag(tag, value)?,
+			_ => dictionary(v)?,
+		},
+	})
+}
+
+fn tagged(tag: &str, value: Value) -> Value {
+	let mut map = Map::with_capacity(1);
+	map.insert(tag.to_owned(), value);
+	Value::Object(map)
+}
+
+fn number(number: &Number) -> Result<Message, JsonError> {
+	if let Some(v) = number.as_i64() {
+		Ok(Message::Int64(v))
+	} else if let Some(v) = number.as_u64() {
+		Ok(Message::Uint64(v))
+	} else {
+		number
+			.as_f64()
+			.map(Message::Double)
+			.ok_or_else(|| JsonError::InvalidNumber(number.to_string()))
+	}
+}
+
+fn dictionary(map: &Map<String, Value>) -> Result<Message, JsonError> {
+	Ok(Message::Dictionary(
+		map.iter()
+			.map(|(key, value)| Ok((CString::new(key.as_str())?, from_json(value)?)))
+			.collect::<Result<HashMap<_, _>, JsonError>>()?,
+	))
+}
+
+fn untag(tag: &str, value: &Value) -> Result<Message, JsonError> {
+	match (tag, value) {
+		(UINT64_TAG, Value::Number(v)) => v
+			.as_u64()
+			.map(Message::Uint64)
+			.ok_or_else(|| JsonError::InvalidTag(UINT64_TAG.to_owned())),
+		(DOUBLE_TAG, Value::String(v)) => match v.as_str() {
+			"NaN" => Ok(Message::Double(f64::NAN)),
+			"Infinity" => Ok(Message::Double(f64::INFINITY)),
+			"-Infinity" => Ok(Message::Double(f64::NEG_INFINITY)),
+			_ => Err(JsonError::InvalidTag(DOUBLE_TAG.to_owned())),
+		},
+		(DATA_TAG, Value::String(v)) => Ok(Message::Data(base64::decode(v)?)),
+		(UUID_TAG, Value::String(v)) => v
+			.parse::<Uuid>()
+			.map(|uuid| Message::Uuid(uuid.as_bytes().to_vec()))
+			.map_err(|_| JsonError::InvalidTag(UUID_TAG.to_owned())),
+		(DICTIONARY_TAG, Value::Object(v)) => dictionary(v),
+		_ => Err(JsonError::InvalidTag(tag.to_owned())),
+	}
+}
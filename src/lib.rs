@@ -8,22 +8,114 @@
 
 use xpc_connection::Message;
 
+pub mod canonical;
+mod date;
 mod de;
+mod describe;
 pub mod error;
+#[cfg(feature = "json")]
+pub mod json;
+mod macros;
+#[cfg(feature = "plist")]
+pub mod plist;
+pub mod rpc;
 mod ser;
+pub mod text;
+mod uuid;
+mod value;
+pub mod wire;
+
+pub use date::Date;
+pub use de::{DeserializerConfig, NumericCoercion, UuidCoercion};
+pub use describe::{Description, DescriptionConfig};
+pub use macros::xpc_service;
+pub use ser::{IntegerEncoding, SerializerConfig};
+pub use uuid::Uuid;
+pub use value::{from_value, to_value, Value, ValueIndex};
+
+#[doc(hidden)]
+pub use macros::private as __private;
+
+/// How [i128] and [u128] values are represented, since XPC has no native
+/// 128-bit integer type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WideIntegerEncoding {
+	/// A 16-byte [Message::Data] holding the big-endian two's complement
+	/// representation of the value.
+	#[default]
+	Data,
+	/// A [Message::String] holding the value in decimal.
+	String,
+	/// A two-element [Message::Array] of [Message::Uint64]s, holding the high
+	/// and low 64 bits of the two's complement representation, in that order.
+	Halves,
+}
+
+/// How enum variants are laid out as messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EnumRepresentation {
+	/// Unit variants are a [Message::String] holding the variant name, other
+	/// variants are a single-key [Message::Dictionary] mapping the variant
+	/// name to its contents.
+	#[default]
+	ExternallyTagged,
+	/// Unit variants are a [Message::Uint64] holding the variant index, other
+	/// variants are a [Message::Dictionary] with the variant index under
+	/// `"type"` and its contents under `"value"`. The index is always written
+	/// as a [Message::Uint64], whatever the [IntegerEncoding], and read from
+	/// either integer kind, whatever the [NumericCoercion].
+	Index,
+	/// Every variant is a [Message::Dictionary] with the variant name under
+	/// `"type"` and, unless it is a unit variant, its contents under `"value"`.
+	TypeValue,
+}
+
+pub(crate) const ENUM_TYPE_KEY: &str = "type";
+pub(crate) const ENUM_VALUE_KEY: &str = "value";
 
 pub fn serialize<T>(v: &T) -> Result<Message, error::SerializeError>
 where
 	T: serde::Serialize,
 {
-	v.serialize(&mut ser::XpcSerializer)
+	SerializerConfig::default().serialize(v)
 }
 
 pub fn deserialize<'de, T>(message: Message) -> Result<T, error::DeserializeError>
 where
 	T: serde::Deserialize<'de>,
 {
-	T::deserialize(de::XpcDeserializer { message })
+	DeserializerConfig::default().deserialize(message)
+}
+
+/// Deserializes a reply, or the [XpcPeerError](error::XpcPeerError) libxpc
+/// delivered in its place as a [Message::Error].
+///
+/// Use this instead of deserializing into `Result<T, XpcPeerError>`, which
+/// only reads results this crate serialized, and rejects a bare
+/// [Message::Error].
+pub fn deserialize_reply<'de, T>(
+	message: Message,
+) -> Result<Result<T, error::XpcPeerError>, error::DeserializeError>
+where
+	T: serde::Deserialize<'de>,
+{
+	DeserializerConfig::default().deserialize_reply(message)
+}
+
+/// Deserializes a borrowed [Message]. Unlike [deserialize], strings and bytes
+/// can be borrowed from the message, so `&str`, `&[u8]` and `Cow` fields are
+/// supported and large [Message::Data] blobs are never copied.
+pub fn from_message_ref<'de, T>(message: &'de Message) -> Result<T, error::DeserializeError>
+where
+	T: serde::Deserialize<'de>,
+{
+	DeserializerConfig::default().deserialize_ref(message)
+}
+
+/// Describes a [Message] in the style of `xpc_copy_description`, for logging.
+/// Use [DescriptionConfig] to change how it is laid out.
+pub fn describe(message: &Message) -> Description<'_> {
+	DescriptionConfig::default().describe(message)
 }
 
 pub(crate) fn xpc_message_to_type(message: &Message) -> &'static str {
@@ -37,6 +129,8 @@ pub(crate) fn xpc_message_to_type(message: &Message) -> &'static str {
 		Message::Data(_) => "bytes",
 		Message::Uint64(_) => "u64",
 		Message::Null => "null",
+		Message::Uuid(_) => "uuid",
+		Message::Error(_) => "error",
 		_ => "invalid",
 	}
 }
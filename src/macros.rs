@@ -0,0 +1,200 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+/// Builds a [Message](xpc_connection::Message) from a JSON-like literal.
+///
+/// `null`, arrays and dictionaries are built directly; any other expression
+/// is serialized with [serialize](crate::serialize), so it can be a variable
+/// or a value of any type implementing [Serialize](serde::Serialize).
+/// Dictionary keys are either string literals or parenthesized expressions.
+///
+/// ```
+/// # use xpc_connection::Message;
+/// let port = 8080u16;
+/// let message = xpc_serde::xpc!({
+///     "name": "main",
+///     "port": port,
+///     "tags": ["a", "b"],
+///     "parent": null,
+///     (format!("key{}", 1)): { "n": 42u64 },
+/// });
+/// assert!(matches!(message, Message::Dictionary(_)));
+/// ```
+///
+/// # Panics
+///
+/// Panics if an interpolated value fails to serialize or an expression key
+/// contains a NUL byte. A string literal key containing a NUL byte is
+/// rejected at compile time instead:
+///
+/// ```compile_fail
+/// let message = xpc_serde::xpc!({ "a\0b": 1 });
+/// ```
+#[macro_export]
+macro_rules! xpc {
+	(null) => {
+		$crate::__private::Message::Null
+	};
+	([]) => {
+		$crate::__private::Message::Array(::std::vec::Vec::new())
+	};
+	([ $($tt:tt)+ ]) => {
+		$crate::__private::Message::Array($crate::__xpc_internal!(@array [] $($tt)+))
+	};
+	({}) => {
+		$crate::__private::Message::Dictionary(::std::collections::HashMap::new())
+	};
+	({ $($tt:tt)+ }) => {
+		$crate::__private::Message::Dictionary({
+			let mut map = ::std::collections::HashMap::new();
+			$crate::__xpc_internal!(@object map $($tt)+);
+			map
+		})
+	};
+	($other:expr) => {
+		$crate::__private::serialize(&$other)
+	};
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! __xpc_internal {
+	// Munches array elements into a list of expressions.
+	(@array [$($elements:expr,)*]) => {
+		::std::vec![$($elements,)*]
+	};
+	(@array [$($elements:expr,)*] null $(, $($rest:tt)*)?) => {
+		$crate::__xpc_internal!(@array [$($elements,)* $crate::xpc!(null),] $($($rest)*)?)
+	};
+	(@array [$($elements:expr,)*] [$($array:tt)*] $(, $($rest:tt)*)?) => {
+		$crate::__xpc_internal!(@array [$($elements,)* $crate::xpc!([$($array)*]),] $($($rest)*)?)
+	};
+	(@array [$($elements:expr,)*] {$($map:tt)*} $(, $($rest:tt)*)?) => {
+		$crate::__xpc_internal!(@array [$($elements,)* $crate::xpc!({$($map)*}),] $($($rest)*)?)
+	};
+	(@array [$($elements:expr,)*] $next:expr $(, $($rest:tt)*)?) => {
+		$crate::__xpc_internal!(@array [$($elements,)* $crate::xpc!($next),] $($($rest)*)?)
+	};
+
+	// Munches dictionary entries, inserting each into the map as it goes.
+	(@object $map:ident) => {};
+	(@object $map:ident $key:literal : $($rest:tt)+) => {
+		$crate::__xpc_internal!(@entry $map ({
+			const _: () = $crate::__private::assert_key($key);
+			$crate::__private::key($key)
+		}) $($rest)+);
+	};
+	(@object $map:ident ($key:expr) : $($rest:tt)+) => {
+		$crate::__xpc_internal!(@entry $map ($crate::__private::key($key)) $($rest)+);
+	};
+	(@entry $map:ident ($key:expr) null $(, $($rest:tt)*)?) => {
+		let _ = $map.insert($key, $crate::xpc!(null));
+		$crate::__xpc_internal!(@object $map $($($rest)*)?);
+	};
+	(@entry $map:ident ($key:expr) [$($array:tt)*] $(, $($rest:tt)*)?) => {
+		let _ = $map.insert($key, $crate::xpc!([$($array)*]));
+		$crate::__xpc_internal!(@object $map $($($rest)*)?);
+	};
+	(@entry $map:ident ($key:expr) {$($object:tt)*} $(, $($rest:tt)*)?) => {
+		let _ = $map.insert($key, $crate::xpc!({$($object)*}));
+		$crate::__xpc_internal!(@object $map $($($rest)*)?);
+	};
+	(@entry $map:ident ($key:expr) $value:expr $(, $($rest:tt)*)?) => {
+		let _ = $map.insert($key, $crate::xpc!($value));
+		$crate::__xpc_internal!(@object $map $($($rest)*)?);
+	};
+}
+
+/// Declares an XPC service as a trait, and generates a typed client and a
+/// server that dispatches requests to an implementation of the trait.
+///
+/// For a trait `Name`, the attribute defines a `NameClient<T>` with the same
+/// methods, each making a call over a [Transport](crate::rpc::Transport) with
+/// [rpc::Client](crate::rpc::Client), and a `NameServer<T, S>` that answers
+/// those calls with a service `S` implementing the trait. Each request is a
+/// dictionary mapping the method name to a dictionary of its arguments, and
+/// each reply maps the method name to its return value. Requests for a method
+/// the trait doesn't have are answered with [RpcError::UnknownMethod], and
+/// requests with arguments that don't deserialize with
+/// [RpcError::Deserialize]; the server keeps serving either way.
+///
+/// Methods take `&self` and arguments that implement
+/// [Serialize](serde::Serialize) and [Deserialize](serde::Deserialize), as do
+/// their return values. They can't be generic or async.
+///
+/// [RpcError::UnknownMethod]: crate::error::RpcError::UnknownMethod
+/// [RpcError::Deserialize]: crate::error::RpcError::Deserialize
+///
+/// ```
+/// use xpc_serde::xpc_service;
+///
+/// #[xpc_service]
+/// pub trait Indexer {
+///     fn reindex(&self, path: String) -> Result<u64, String>;
+/// }
+///
+/// struct Files;
+///
+/// impl Indexer for Files {
+///     fn reindex(&self, path: String) -> Result<u64, String> {
+///         Ok(path.len() as u64)
+///     }
+/// }
+///
+/// let (client, server) = xpc_serde::rpc::loopback();
+/// let server = std::thread::spawn(move || IndexerServer::new(server, Files).serve());
+///
+/// let mut client = IndexerClient::new(client);
+/// assert_eq!(client.reindex("/tmp".to_string()).unwrap(), Ok(4));
+///
+/// drop(client);
+/// server.join().unwrap().unwrap();
+/// ```
+pub use xpc_serde_macros::xpc_service;
+
+/// Support functions for [xpc!] and [xpc_service]. Not public API.
+pub mod private {
+	pub use serde;
+	use serde::Serialize;
+	use std::ffi::CString;
+	pub use xpc_connection::Message;
+
+	/// Fails constant evaluation if a literal key contains a NUL byte.
+	pub const fn assert_key(key: &str) {
+		let bytes = key.as_bytes();
+		let mut i = 0;
+		while i < bytes.len() {
+			if bytes[i] == 0 {
+				panic!("xpc! dictionary keys must not contain NUL bytes");
+			}
+			i += 1;
+		}
+	}
+
+	pub fn key<K: Into<Vec<u8>>>(key: K) -> CString {
+		CString::new(key).expect("xpc! dictionary keys must not contain NUL bytes")
+	}
+
+	pub fn serialize<T: Serialize + ?Sized>(v: &T) -> Message {
+		crate::SerializerConfig::default()
+			.serialize(v)
+			.expect("failed to serialize xpc! value")
+	}
+
+	/// The method a service request calls, if the service doesn't have it.
+	pub fn unknown_method(request: &Message, methods: &[&str]) -> Option<String> {
+		match request {
+			Message::Dictionary(map) if map.len() == 1 => map
+				.keys()
+				.map(|method| method.to_string_lossy())
+				.find(|method| !methods.contains(&&**method))
+				.map(|method| method.into_owned()),
+			_ => None,
+		}
+	}
+}
@@ -0,0 +1,158 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Conversion between [Message] and property lists.
+//!
+//! Property lists have fewer types than XPC, so a few conversions are not
+//! exact:
+//!
+//! * Plist integers don't record their signedness. They become a
+//!   [Message::Int64] when they fit, and a [Message::Uint64] otherwise.
+//! * Plist dates become a [Message::Int64] holding nanoseconds since the Unix
+//!   epoch. Integers never turn back into dates.
+//! * Plists have no null. [Message::Null] dictionary entries, which is how
+//!   `None` fields are serialized, are left out, and a null anywhere else is
+//!   an error.
+//! * Plists have no UUIDs. A [Message::Uuid] becomes a hyphenated string,
+//!   which becomes a [Message::String] again. [from_reader] and [from_file]
+//!   deserialize with [UuidCoercion::Lenient], so [Uuid]s read back.
+
+use crate::{error::PlistError, xpc_message_to_type, Date, DeserializerConfig, Uuid, UuidCoercion};
+use ::plist::{Dictionary, Value};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	collections::HashMap,
+	convert::TryFrom,
+	ffi::CString,
+	io::{Read, Seek, Write},
+	path::Path,
+	time::SystemTime,
+};
+use xpc_connection::Message;
+
+/// Converts a [Message] into a plist value.
+pub fn to_plist(message: &Message) -> Result<Value, PlistError> {
+	Ok(match message {
+		Message::Bool(v) => Value::Boolean(*v),
+		Message::Int64(v) => Value::Integer((*v).into()),
+		Message::Uint64(v) => Value::Integer((*v).into()),
+		Message::Double(v) => Value::Real(*v),
+		Message::String(v) => Value::String(v.to_str()?.to_owned()),
+		Message::Data(v) => Value::Data(v.clone()),
+		Message::Uuid(v) => Value::String(
+			Uuid::try_from(v.as_slice())
+				.map_err(|_| PlistError::InvalidUuid(v.len()))?
+				.to_string(),
+		),
+		Message::Array(v) => Value::Array(v.iter().map(to_plist).collect::<Result<_, _>>()?),
+		Message::Dictionary(v) => {
+			let mut dictionary = Dictionary::new();
+			for (key, value) in v {
+				if let Message::Null = value {
+					continue;
+				}
+				dictionary.insert(key.to_str()?.to_owned(), to_plist(value)?);
+			}
+			Value::Dictionary(dictionary)
+		}
+		_ => return Err(PlistError::Unsupported(xpc_message_to_type(message))),
+	})
+}
+
+/// Converts a plist value into a [Message].
+pub fn from_plist(value: &Value) -> Result<Message, PlistError> {
+	Ok(match value {
+		Value::Boolean(v) => Message::Bool(*v),
+		Value::Integer(v) => match v.as_signed() {
+			Some(v) => Message::Int64(v),
+			None => Message::Uint64(v.as_unsigned().ok_or(PlistError::Unsupported("integer"))?),
+		},
+		Value::Real(v) => Message::Double(*v),
+		Value::String(v) => Message::String(CString::new(v.as_str())?),
+		Value::Data(v) => Message::Data(v.clone()),
+		Value::Date(v) => Message::Int64(Date::try_from(SystemTime::from(*v))?.nanoseconds()),
+		Value::Array(v) => Message::Array(v.iter().map(from_plist).collect::<Result<_, _>>()?),
+		Value::Dictionary(v) => Message::Dictionary(
+			v.iter()
+				.map(|(key, value)| Ok((CString::new(key.as_str())?, from_plist(value)?)))
+				.collect::<Result<HashMap<_, _>, PlistError>>()?,
+		),
+		Value::Uid(_) => return Err(PlistError::Unsupported("uid")),
+		_ => return Err(PlistError::Unsupported("unknown plist value")),
+	})
+}
+
+/// Deserializes a value from an XML or binary plist.
+pub fn from_reader<R, T>(reader: R) -> Result<T, PlistError>
+where
+	R: Read + Seek,
+	T: DeserializeOwned,
+{
+	deserialize(&Value::from_reader(reader)?)
+}
+
+/// Deserializes a value from an XML or binary plist file.
+pub fn from_file<P, T>(path: P) -> Result<T, PlistError>
+where
+	P: AsRef<Path>,
+	T: DeserializeOwned,
+{
+	deserialize(&Value::from_file(path)?)
+}
+
+fn deserialize<T>(value: &Value) -> Result<T, PlistError>
+where
+	T: DeserializeOwned,
+{
+	Ok(DeserializerConfig::new()
+		.uuid_coercion(UuidCoercion::Lenient)
+		.deserialize(from_plist(value)?)?)
+}
+
+fn serialize<T>(v: &T) -> Result<Value, PlistError>
+where
+	T: Serialize,
+{
+	to_plist(&crate::serialize(v)?)
+}
+
+/// Serializes a value as an XML plist.
+pub fn to_writer_xml<W, T>(writer: W, v: &T) -> Result<(), PlistError>
+where
+	W: Write,
+	T: Serialize,
+{
+	Ok(serialize(v)?.to_writer_xml(writer)?)
+}
+
+/// Serializes a value as a binary plist.
+pub fn to_writer_binary<W, T>(writer: W, v: &T) -> Result<(), PlistError>
+where
+	W: Write,
+	T: Serialize,
+{
+	Ok(serialize(v)?.to_writer_binary(writer)?)
+}
+
+/// Serializes a value into an XML plist file.
+pub fn to_file_xml<P, T>(path: P, v: &T) -> Result<(), PlistError>
+where
+	P: AsRef<Path>,
+	T: Serialize,
+{
+	Ok(serialize(v)?.to_file_xml(path)?)
+}
+
+/// Serializes a value into a binary plist file.
+pub fn to_file_binary<P, T>(path: P, v: &T) -> Result<(), PlistError>
+where
+	P: AsRef<Path>,
+	T: Serialize,
+{
+	Ok(serialize(v)?.to_file_binary(path)?)
+}
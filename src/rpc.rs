@@ -0,0 +1,364 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Typed request/reply calls over any [Transport].
+//!
+//! Requests and replies are serialized as dictionaries, with the request id
+//! stored under [ID_KEY] so replies can be matched to the request they answer.
+//! A [Server] answers requests it can't handle with a description of the error
+//! under [ERROR_KEY], which the [Client] returns as [RpcError::Remote].
+//! Connection errors libxpc delivers in place of a reply are returned as
+//! [RpcError::Peer].
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use xpc_serde::rpc::{loopback, Client, Server};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Add {
+//!     a: u64,
+//!     b: u64,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Sum {
+//!     sum: u64,
+//! }
+//!
+//! let (client, server) = loopback();
+//! let server = std::thread::spawn(move || {
+//!     Server::new(server).serve(|request: Add| Sum { sum: request.a + request.b })
+//! });
+//!
+//! let mut client = Client::new(client);
+//! let reply: Sum = client.call(&Add { a: 1, b: 2 }).unwrap();
+//! assert_eq!(reply.sum, 3);
+//!
+//! drop(client);
+//! server.join().unwrap().unwrap();
+//! ```
+
+use crate::error::{RpcError, XpcPeerError};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	collections::HashMap,
+	ffi::CString,
+	sync::mpsc::{channel, Receiver, Sender},
+};
+use xpc_connection::Message;
+
+/// The dictionary key requests and replies carry their id under. Request and
+/// reply types can't have a field with this name, and fail to encode with
+/// [RpcError::ReservedKey] if they do.
+pub const ID_KEY: &str = "rpc_id";
+
+/// The dictionary key a [Server] stores the description of an error under,
+/// when it replies to a request it couldn't handle. The reply has no id if
+/// the request didn't have one.
+pub const ERROR_KEY: &str = "rpc_error";
+
+/// A connection messages can be sent over and received from, one at a time.
+pub trait Transport {
+	fn send(&mut self, message: Message);
+
+	/// Waits for the next message, returning [None] once the connection is
+	/// closed. Connection errors are received as a [Message::Error].
+	fn receive(&mut self) -> Option<Message>;
+}
+
+impl<T> Transport for &mut T
+where
+	T: Transport + ?Sized,
+{
+	fn send(&mut self, message: Message) {
+		(**self).send(message)
+	}
+
+	fn receive(&mut self) -> Option<Message> {
+		(**self).receive()
+	}
+}
+
+/// One end of an in-memory connection, created by [loopback]. Messages sent
+/// on one end are received on the other.
+pub struct Loopback {
+	sender: Sender<Message>,
+	receiver: Receiver<Message>,
+}
+
+/// Creates a pair of connected in-memory transports, for testing clients and
+/// servers without libxpc. Dropping one end closes the connection for the
+/// other.
+pub fn loopback() -> (Loopback, Loopback) {
+	let (a_sender, b_receiver) = channel();
+	let (b_sender, a_receiver) = channel();
+	(
+		Loopback {
+			sender: a_sender,
+			receiver: a_receiver,
+		},
+		Loopback {
+			sender: b_sender,
+			receiver: b_receiver,
+		},
+	)
+}
+
+impl Transport for Loopback {
+	fn send(&mut self, message: Message) {
+		// Like XPC, sending to a peer that has gone away is silently dropped.
+		let _ = self.sender.send(message);
+	}
+
+	fn receive(&mut self) -> Option<Message> {
+		self.receiver.recv().ok()
+	}
+}
+
+/// Serializes a request or reply and stores `id` in it.
+fn encode<T>(value: &T, id: u64) -> Result<Message, RpcError>
+where
+	T: Serialize,
+{
+	match crate::serialize(value)? {
+		Message::Dictionary(mut map) => {
+			if map.contains_key(&key(ERROR_KEY)) {
+				return Err(RpcError::ReservedKey(ERROR_KEY));
+			}
+			if map.insert(key(ID_KEY), Message::Uint64(id)).is_some() {
+				return Err(RpcError::ReservedKey(ID_KEY));
+			}
+			Ok(Message::Dictionary(map))
+		}
+		message => Err(RpcError::NotADictionary(crate::xpc_message_to_type(
+			&message,
+		))),
+	}
+}
+
+/// Splits the id, if there is one, off a received request or reply. Ids are
+/// sent as a uint64, but a non-negative int64 from a C peer is accepted too.
+fn decode(message: Message) -> Result<(Option<u64>, Dictionary), RpcError> {
+	match message {
+		Message::Error(error) => Err(XpcPeerError::from(error).into()),
+		Message::Dictionary(mut map) => match map.remove(&key(ID_KEY)) {
+			Some(Message::Uint64(id)) => Ok((Some(id), map)),
+			Some(Message::Int64(id)) if id >= 0 => Ok((Some(id as u64), map)),
+			Some(Message::Int64(_)) => Err(RpcError::InvalidId("negative i64")),
+			Some(id) => Err(RpcError::InvalidId(crate::xpc_message_to_type(&id))),
+			None => Ok((None, map)),
+		},
+		message => Err(RpcError::NotADictionary(crate::xpc_message_to_type(
+			&message,
+		))),
+	}
+}
+
+/// The reply a [Server] sends to a request it couldn't handle.
+fn encode_error(error: &RpcError, id: Option<u64>) -> Message {
+	let description = error.to_string().replace('\0', "\\0");
+	let mut map = Dictionary::new();
+	map.insert(
+		key(ERROR_KEY),
+		Message::String(CString::new(description).expect("NUL bytes were escaped")),
+	);
+	if let Some(id) = id {
+		map.insert(key(ID_KEY), Message::Uint64(id));
+	}
+	Message::Dictionary(map)
+}
+
+/// The error a [Server] replied with, if the reply is one.
+fn decode_error(reply: &Dictionary) -> Option<RpcError> {
+	match reply.get(&key(ERROR_KEY)) {
+		Some(Message::String(description)) => {
+			Some(RpcError::Remote(description.to_string_lossy().into_owned()))
+		}
+		_ => None,
+	}
+}
+
+type Dictionary = HashMap<CString, Message>;
+
+fn key(key: &'static str) -> CString {
+	CString::new(key).expect("rpc keys contain no NUL bytes")
+}
+
+/// Sends requests and waits for their replies.
+pub struct Client<T> {
+	transport: T,
+	next_id: u64,
+	/// Replies that arrived while waiting for a different one.
+	pending: HashMap<u64, Dictionary>,
+}
+
+impl<T> Client<T>
+where
+	T: Transport,
+{
+	pub fn new(transport: T) -> Self {
+		Self {
+			transport,
+			next_id: 0,
+			pending: HashMap::new(),
+		}
+	}
+
+	/// Sends a request without waiting for the reply, returning its id to
+	/// pass to [Client::receive].
+	pub fn send<Req>(&mut self, request: &Req) -> Result<u64, RpcError>
+	where
+		Req: Serialize,
+	{
+		let id = self.next_id;
+		let message = encode(request, id)?;
+		self.next_id += 1;
+		self.transport.send(message);
+		Ok(id)
+	}
+
+	/// Waits for the reply to the request with the given id. Replies to other
+	/// requests that arrive first are kept until they are asked for.
+	pub fn receive<Rep>(&mut self, id: u64) -> Result<Rep, RpcError>
+	where
+		Rep: DeserializeOwned,
+	{
+		let reply = match self.pending.remove(&id) {
+			Some(reply) => reply,
+			None => loop {
+				let message = self.transport.receive().ok_or(RpcError::Closed)?;
+				match decode(message)? {
+					(Some(reply_id), reply) if reply_id == id => break reply,
+					(Some(reply_id), reply) => {
+						self.pending.insert(reply_id, reply);
+					}
+					// The server couldn't tell which request this answers, so
+					// it fails whichever call is waiting.
+					(None, reply) => {
+						return Err(decode_error(&reply).unwrap_or(RpcError::MissingId))
+					}
+				}
+			},
+		};
+		match decode_error(&reply) {
+			Some(error) => Err(error),
+			None => Ok(crate::deserialize(Message::Dictionary(reply))?),
+		}
+	}
+
+	/// Sends a request and waits for its reply.
+	pub fn call<Req, Rep>(&mut self, request: &Req) -> Result<Rep, RpcError>
+	where
+		Req: Serialize,
+		Rep: DeserializeOwned,
+	{
+		let id = self.send(request)?;
+		self.receive(id)
+	}
+
+	pub fn into_inner(self) -> T {
+		self.transport
+	}
+}
+
+/// Receives requests and sends back the replies a handler produces.
+pub struct Server<T> {
+	transport: T,
+}
+
+impl<T> Server<T>
+where
+	T: Transport,
+{
+	pub fn new(transport: T) -> Self {
+		Self { transport }
+	}
+
+	/// Handles a single request, returning `false` if the connection was
+	/// closed before one arrived.
+	///
+	/// If the request can't be handled, the client is sent an error reply
+	/// before the error is returned.
+	pub fn handle_one<Req, Rep, F>(&mut self, handler: F) -> Result<bool, RpcError>
+	where
+		Req: DeserializeOwned,
+		Rep: Serialize,
+		F: FnOnce(Req) -> Rep,
+	{
+		self.handle_message(|request| Ok(handler(crate::deserialize(request)?)))
+	}
+
+	/// Like [Server::handle_one], but passes the handler the request message
+	/// itself, without its id, and lets it fail. Errors the handler returns
+	/// are sent to the client like any other.
+	pub fn handle_message<Rep, F>(&mut self, handler: F) -> Result<bool, RpcError>
+	where
+		Rep: Serialize,
+		F: FnOnce(Message) -> Result<Rep, RpcError>,
+	{
+		let message = match self.transport.receive() {
+			Some(message) => message,
+			None => return Ok(false),
+		};
+		let (id, request) = match decode(message) {
+			Ok(decoded) => decoded,
+			Err(error @ RpcError::Peer(_)) => return Err(error),
+			Err(error) => return Err(self.reject(error, None)),
+		};
+		let id = match id {
+			Some(id) => id,
+			None => return Err(self.reject(RpcError::MissingId, None)),
+		};
+		match handler(Message::Dictionary(request)).and_then(|reply| encode(&reply, id)) {
+			Ok(reply) => {
+				self.transport.send(reply);
+				Ok(true)
+			}
+			Err(error) => Err(self.reject(error, Some(id))),
+		}
+	}
+
+	/// Handles requests until the connection is closed. Requests that can't
+	/// be handled are answered with an error and skipped; only a connection
+	/// error stops the server early.
+	pub fn serve<Req, Rep, F>(&mut self, mut handler: F) -> Result<(), RpcError>
+	where
+		Req: DeserializeOwned,
+		Rep: Serialize,
+		F: FnMut(Req) -> Rep,
+	{
+		self.serve_messages(|request| Ok(handler(crate::deserialize(request)?)))
+	}
+
+	/// Like [Server::serve], but with a handler like
+	/// [Server::handle_message]'s.
+	pub fn serve_messages<Rep, F>(&mut self, mut handler: F) -> Result<(), RpcError>
+	where
+		Rep: Serialize,
+		F: FnMut(Message) -> Result<Rep, RpcError>,
+	{
+		loop {
+			match self.handle_message(&mut handler) {
+				Ok(true) => {}
+				Ok(false) => return Ok(()),
+				Err(error @ RpcError::Peer(_)) => return Err(error),
+				Err(_) => {}
+			}
+		}
+	}
+
+	/// Sends the client an error reply, returning the error.
+	fn reject(&mut self, error: RpcError, id: Option<u64>) -> RpcError {
+		self.transport.send(encode_error(&error, id));
+		error
+	}
+
+	pub fn into_inner(self) -> T {
+		self.transport
+	}
+}
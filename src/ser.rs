@@ -6,15 +6,164 @@
 	file, You can obtain one at http://mozilla.org/MPL/2.0/.
 */
 
-use crate::{error::SerializeError, xpc_message_to_type};
+use crate::{
+	date::DATE_NAME,
+	error::{PathSegment, SerializeError},
+	uuid::{Uuid, UUID_NAME},
+	EnumRepresentation, WideIntegerEncoding, ENUM_TYPE_KEY, ENUM_VALUE_KEY,
+};
 use serde::ser::{
-	Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
-	SerializeTupleStruct, SerializeTupleVariant, Serializer,
+	Impossible, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+	SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
 };
-use std::{collections::HashMap, ffi::CString};
+use std::{collections::HashMap, convert::TryFrom, ffi::CString};
 use xpc_connection::Message;
 
-pub(crate) struct XpcSerializer;
+/// Which XPC integer type the serializer emits for Rust integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IntegerEncoding {
+	/// Signed integers become [Message::Int64], unsigned integers become
+	/// [Message::Uint64].
+	#[default]
+	PreserveSignedness,
+	/// Every integer becomes [Message::Int64], unless it is larger than
+	/// [i64::MAX].
+	PreferInt64,
+	/// Every integer becomes [Message::Uint64], unless it is negative.
+	PreferUint64,
+}
+
+/// Options controlling how a value is serialized into a [Message].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SerializerConfig {
+	integer_encoding: IntegerEncoding,
+	wide_integer_encoding: WideIntegerEncoding,
+	enum_representation: EnumRepresentation,
+}
+
+impl SerializerConfig {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Sets which XPC integer type integers are serialized as.
+	/// Defaults to [IntegerEncoding::PreserveSignedness].
+	pub fn integer_encoding(mut self, integer_encoding: IntegerEncoding) -> Self {
+		self.integer_encoding = integer_encoding;
+		self
+	}
+
+	/// Sets how [i128] and [u128] values are serialized.
+	/// Defaults to [WideIntegerEncoding::Data].
+	pub fn wide_integer_encoding(mut self, wide_integer_encoding: WideIntegerEncoding) -> Self {
+		self.wide_integer_encoding = wide_integer_encoding;
+		self
+	}
+
+	/// Sets how enum variants are laid out.
+	/// Defaults to [EnumRepresentation::ExternallyTagged].
+	pub fn enum_representation(mut self, enum_representation: EnumRepresentation) -> Self {
+		self.enum_representation = enum_representation;
+		self
+	}
+
+	/// Serializes a value into a [Message] using this configuration.
+	pub fn serialize<T>(&self, v: &T) -> Result<Message, SerializeError>
+	where
+		T: Serialize + ?Sized,
+	{
+		v.serialize(&mut XpcSerializer { config: *self })
+	}
+}
+
+pub(crate) struct XpcSerializer {
+	pub(crate) config: SerializerConfig,
+}
+
+impl XpcSerializer {
+	fn signed(&self, v: i64) -> Message {
+		match self.config.integer_encoding {
+			IntegerEncoding::PreferUint64 if v >= 0 => Message::Uint64(v as u64),
+			_ => Message::Int64(v),
+		}
+	}
+
+	fn unsigned(&self, v: u64) -> Message {
+		match self.config.integer_encoding {
+			IntegerEncoding::PreferInt64 if v <= i64::MAX as u64 => Message::Int64(v as i64),
+			_ => Message::Uint64(v),
+		}
+	}
+
+	fn wide(&self, bits: u128, decimal: &dyn std::fmt::Display) -> Result<Message, SerializeError> {
+		Ok(match self.config.wide_integer_encoding {
+			WideIntegerEncoding::Data => Message::Data(bits.to_be_bytes().to_vec()),
+			WideIntegerEncoding::String => Message::String(CString::new(decimal.to_string())?),
+			WideIntegerEncoding::Halves => Message::Array(vec![
+				Message::Uint64((bits >> 64) as u64),
+				Message::Uint64(bits as u64),
+			]),
+		})
+	}
+
+	fn unit_variant(
+		&self,
+		variant_index: u32,
+		variant: &'static str,
+	) -> Result<Message, SerializeError> {
+		match self.config.enum_representation {
+			EnumRepresentation::ExternallyTagged => Ok(Message::String(CString::new(variant)?)),
+			EnumRepresentation::Index => Ok(Message::Uint64(u64::from(variant_index))),
+			EnumRepresentation::TypeValue => {
+				let mut dict = HashMap::<CString, Message>::with_capacity(1);
+				dict.insert(
+					CString::new(ENUM_TYPE_KEY)?,
+					Message::String(CString::new(variant)?),
+				);
+				Ok(Message::Dictionary(dict))
+			}
+		}
+	}
+
+	/// The path segment an enum variant's value is serialized under.
+	fn variant_segment(&self, variant: &'static str) -> PathSegment {
+		match self.config.enum_representation {
+			EnumRepresentation::ExternallyTagged => PathSegment::Key(variant.to_owned()),
+			EnumRepresentation::Index | EnumRepresentation::TypeValue => {
+				PathSegment::Key(ENUM_VALUE_KEY.to_owned())
+			}
+		}
+	}
+
+	fn variant(
+		&self,
+		variant_index: u32,
+		variant: &'static str,
+		value: Message,
+	) -> Result<Message, SerializeError> {
+		let mut dict = HashMap::<CString, Message>::with_capacity(2);
+		match self.config.enum_representation {
+			EnumRepresentation::ExternallyTagged => {
+				dict.insert(CString::new(variant)?, value);
+			}
+			EnumRepresentation::Index => {
+				dict.insert(
+					CString::new(ENUM_TYPE_KEY)?,
+					Message::Uint64(u64::from(variant_index)),
+				);
+				dict.insert(CString::new(ENUM_VALUE_KEY)?, value);
+			}
+			EnumRepresentation::TypeValue => {
+				dict.insert(
+					CString::new(ENUM_TYPE_KEY)?,
+					Message::String(CString::new(variant)?),
+				);
+				dict.insert(CString::new(ENUM_VALUE_KEY)?, value);
+			}
+		}
+		Ok(Message::Dictionary(dict))
+	}
+}
 
 impl<'a> Serializer for &'a mut XpcSerializer {
 	type Ok = Message;
@@ -33,35 +182,43 @@ impl<'a> Serializer for &'a mut XpcSerializer {
 	}
 
 	fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Int64(i64::from(v)))
+		Ok(self.signed(i64::from(v)))
 	}
 
 	fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Int64(i64::from(v)))
+		Ok(self.signed(i64::from(v)))
 	}
 
 	fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Int64(i64::from(v)))
+		Ok(self.signed(i64::from(v)))
 	}
 
 	fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Int64(v))
+		Ok(self.signed(v))
 	}
 
 	fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Uint64(u64::from(v)))
+		Ok(self.unsigned(u64::from(v)))
 	}
 
 	fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Uint64(u64::from(v)))
+		Ok(self.unsigned(u64::from(v)))
 	}
 
 	fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Uint64(u64::from(v)))
+		Ok(self.unsigned(u64::from(v)))
 	}
 
 	fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Uint64(v))
+		Ok(self.unsigned(v))
+	}
+
+	fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+		self.wide(v as u128, &v)
+	}
+
+	fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+		self.wide(v, &v)
 	}
 
 	fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
@@ -73,7 +230,7 @@ impl<'a> Serializer for &'a mut XpcSerializer {
 	}
 
 	fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
-		Ok(Message::Uint64(u64::from(v)))
+		Ok(self.unsigned(u64::from(v)))
 	}
 
 	fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
@@ -108,36 +265,56 @@ impl<'a> Serializer for &'a mut XpcSerializer {
 	fn serialize_unit_variant(
 		self,
 		_name: &'static str,
-		_variant_index: u32,
+		variant_index: u32,
 		variant: &'static str,
 	) -> Result<Self::Ok, Self::Error> {
-		self.serialize_str(variant)
+		self.unit_variant(variant_index, variant)
 	}
 
 	fn serialize_newtype_struct<T: ?Sized>(
 		self,
-		_name: &'static str,
+		name: &'static str,
 		value: &T,
 	) -> Result<Self::Ok, Self::Error>
 	where
 		T: serde::Serialize,
 	{
+		if name == DATE_NAME {
+			// Message has no date kind, so dates are always sent as Int64
+			// nanoseconds, regardless of the configured integer encoding.
+			return value.serialize(&mut XpcSerializer {
+				config: SerializerConfig::new(),
+			});
+		}
+		if name == UUID_NAME {
+			// Uuid serializes itself as a string for human-readable formats,
+			// which this is, or as bytes otherwise.
+			let uuid = match value.serialize(&mut *self)? {
+				Message::String(v) => v.to_str().ok().and_then(|v| v.parse().ok()),
+				Message::Data(v) => Uuid::try_from(v.as_slice()).ok(),
+				_ => None,
+			};
+			return uuid
+				.map(|uuid| Message::Uuid(uuid.as_bytes().to_vec()))
+				.ok_or(SerializeError::Expected("uuid"));
+		}
 		value.serialize(self)
 	}
 
 	fn serialize_newtype_variant<T: ?Sized>(
 		self,
 		_name: &'static str,
-		_variant_index: u32,
+		variant_index: u32,
 		variant: &'static str,
 		value: &T,
 	) -> Result<Self::Ok, Self::Error>
 	where
 		T: serde::Serialize,
 	{
-		let mut dict = HashMap::<CString, Message>::with_capacity(2);
-		dict.insert(CString::new(variant)?, value.serialize(self)?);
-		Ok(Message::Dictionary(dict))
+		let value = value
+			.serialize(&mut *self)
+			.map_err(|error| error.at(self.variant_segment(variant)))?;
+		self.variant(variant_index, variant, value)
 	}
 
 	fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
@@ -168,12 +345,13 @@ impl<'a> Serializer for &'a mut XpcSerializer {
 	fn serialize_tuple_variant(
 		self,
 		_name: &'static str,
-		_variant_index: u32,
+		variant_index: u32,
 		variant: &'static str,
 		len: usize,
 	) -> Result<Self::SerializeTupleVariant, Self::Error> {
 		Ok(XpcVariantSerializer {
 			serializer: self,
+			variant_index,
 			variant,
 			sequence: Vec::with_capacity(len),
 		})
@@ -204,14 +382,14 @@ impl<'a> Serializer for &'a mut XpcSerializer {
 	fn serialize_struct_variant(
 		self,
 		_name: &'static str,
-		_variant_index: u32,
+		variant_index: u32,
 		variant: &'static str,
 		len: usize,
 	) -> Result<Self::SerializeStructVariant, Self::Error> {
 		Ok(XpcMapSerializer {
 			serializer: self,
 			map: HashMap::with_capacity(len),
-			variant: Some(variant),
+			variant: Some((variant_index, variant)),
 			key: None,
 		})
 	}
@@ -222,6 +400,20 @@ pub(crate) struct XpcSeqSerializer<'a> {
 	sequence: Vec<Message>,
 }
 
+impl<'a> XpcSeqSerializer<'a> {
+	fn push<T: ?Sized>(&mut self, value: &T) -> Result<(), SerializeError>
+	where
+		T: Serialize,
+	{
+		let index = self.sequence.len();
+		let value = value
+			.serialize(&mut *self.serializer)
+			.map_err(|error| error.at(PathSegment::Index(index)))?;
+		self.sequence.push(value);
+		Ok(())
+	}
+}
+
 impl<'a> SerializeSeq for XpcSeqSerializer<'a> {
 	type Ok = Message;
 	type Error = SerializeError;
@@ -230,8 +422,7 @@ impl<'a> SerializeSeq for XpcSeqSerializer<'a> {
 	where
 		T: Serialize,
 	{
-		self.sequence.push(value.serialize(&mut *self.serializer)?);
-		Ok(())
+		self.push(value)
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -247,8 +438,7 @@ impl<'a> SerializeTuple for XpcSeqSerializer<'a> {
 	where
 		T: Serialize,
 	{
-		self.sequence.push(value.serialize(&mut *self.serializer)?);
-		Ok(())
+		self.push(value)
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -264,8 +454,7 @@ impl<'a> SerializeTupleStruct for XpcSeqSerializer<'a> {
 	where
 		T: Serialize,
 	{
-		self.sequence.push(value.serialize(&mut *self.serializer)?);
-		Ok(())
+		self.push(value)
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -275,6 +464,7 @@ impl<'a> SerializeTupleStruct for XpcSeqSerializer<'a> {
 
 pub(crate) struct XpcVariantSerializer<'a> {
 	serializer: &'a mut XpcSerializer,
+	variant_index: u32,
 	variant: &'static str,
 	sequence: Vec<Message>,
 }
@@ -287,24 +477,51 @@ impl<'a> SerializeTupleVariant for XpcVariantSerializer<'a> {
 	where
 		T: Serialize,
 	{
-		self.sequence.push(value.serialize(&mut *self.serializer)?);
+		let index = self.sequence.len();
+		let value = value.serialize(&mut *self.serializer).map_err(|error| {
+			error
+				.at(PathSegment::Index(index))
+				.at(self.serializer.variant_segment(self.variant))
+		})?;
+		self.sequence.push(value);
 		Ok(())
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
-		let mut dict = HashMap::<CString, Message>::with_capacity(2);
-		dict.insert(CString::new(self.variant)?, Message::Array(self.sequence));
-		Ok(Message::Dictionary(dict))
+		self.serializer.variant(
+			self.variant_index,
+			self.variant,
+			Message::Array(self.sequence),
+		)
 	}
 }
 
 pub(crate) struct XpcMapSerializer<'a> {
 	serializer: &'a mut XpcSerializer,
 	map: HashMap<CString, Message>,
-	variant: Option<&'static str>,
+	variant: Option<(u32, &'static str)>,
 	key: Option<CString>,
 }
 
+impl<'a> XpcMapSerializer<'a> {
+	fn insert_field<T: ?Sized>(
+		&mut self,
+		key: &'static str,
+		value: &T,
+	) -> Result<(), SerializeError>
+	where
+		T: Serialize,
+	{
+		let segment = || PathSegment::Key(key.to_owned());
+		let value = value
+			.serialize(&mut *self.serializer)
+			.map_err(|error| error.at(segment()))?;
+		let key = CString::new(key).map_err(|error| SerializeError::from(error).at(segment()))?;
+		self.map.insert(key, value);
+		Ok(())
+	}
+}
+
 impl<'a> SerializeMap for XpcMapSerializer<'a> {
 	type Ok = Message;
 	type Error = SerializeError;
@@ -313,27 +530,22 @@ impl<'a> SerializeMap for XpcMapSerializer<'a> {
 	where
 		T: Serialize,
 	{
-		let message = key.serialize(&mut *self.serializer)?;
-		match message {
-			Message::String(key) => {
-				self.key = Some(key);
-				Ok(())
-			}
-			_ => panic!(
-				"invalid key: should be string, is {}",
-				xpc_message_to_type(&message)
-			),
-		}
+		let key = key.serialize(MapKeySerializer)?;
+		let segment = PathSegment::Key(key.clone());
+		self.key =
+			Some(CString::new(key).map_err(|error| SerializeError::from(error).at(segment))?);
+		Ok(())
 	}
 
 	fn serialize_value<T: ?Sized>(&mut self, value: &T) -> Result<(), Self::Error>
 	where
 		T: Serialize,
 	{
-		let key = std::mem::take(&mut self.key)
-			.expect("serialize_key() must be called before serialize_value()");
-		self.map
-			.insert(key, value.serialize(&mut *self.serializer)?);
+		let key = std::mem::take(&mut self.key).ok_or(SerializeError::ValueWithoutKey)?;
+		let value = value
+			.serialize(&mut *self.serializer)
+			.map_err(|error| error.at(PathSegment::key(&key)))?;
+		self.map.insert(key, value);
 		Ok(())
 	}
 
@@ -354,10 +566,7 @@ impl<'a> SerializeStruct for XpcMapSerializer<'a> {
 	where
 		T: Serialize,
 	{
-		let key = CString::new(key)?;
-		let value = value.serialize(&mut *self.serializer)?;
-		self.map.insert(key, value);
-		Ok(())
+		self.insert_field(key, value)
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -377,18 +586,198 @@ impl<'a> SerializeStructVariant for XpcMapSerializer<'a> {
 	where
 		T: Serialize,
 	{
-		let key = CString::new(key)?;
-		let value = value.serialize(&mut *self.serializer)?;
-		self.map.insert(key, value);
-		Ok(())
+		let (_, variant) = self.variant.ok_or(SerializeError::MissingVariant)?;
+		self.insert_field(key, value)
+			.map_err(|error| error.at(self.serializer.variant_segment(variant)))
 	}
 
 	fn end(self) -> Result<Self::Ok, Self::Error> {
-		let variant = self
-			.variant
-			.expect("serialize_field() must be called before end()");
-		let mut map = HashMap::<CString, Message>::with_capacity(1);
-		map.insert(CString::new(variant)?, Message::Dictionary(self.map));
-		Ok(Message::Dictionary(map))
+		let (variant_index, variant) = self.variant.ok_or(SerializeError::MissingVariant)?;
+		self.serializer
+			.variant(variant_index, variant, Message::Dictionary(self.map))
+	}
+}
+
+/// Serializes map keys into the strings used as [Message::Dictionary] keys.
+/// NUL bytes are checked by the caller, so the error can name the key.
+///
+/// Strings are used as-is, while integers, bools, chars and unit variants are
+/// stringified. Any other key type results in [SerializeError::InvalidKey].
+pub(crate) struct MapKeySerializer;
+
+impl Serializer for MapKeySerializer {
+	type Ok = String;
+	type Error = SerializeError;
+
+	type SerializeSeq = Impossible<String, SerializeError>;
+	type SerializeTuple = Impossible<String, SerializeError>;
+	type SerializeTupleStruct = Impossible<String, SerializeError>;
+	type SerializeTupleVariant = Impossible<String, SerializeError>;
+	type SerializeMap = Impossible<String, SerializeError>;
+	type SerializeStruct = Impossible<String, SerializeError>;
+	type SerializeStructVariant = Impossible<String, SerializeError>;
+
+	fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(if v { "true" } else { "false" })
+	}
+
+	fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(&v.to_string())
+	}
+
+	fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+		Err(SerializeError::InvalidKey("f32"))
+	}
+
+	fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+		Err(SerializeError::InvalidKey("f64"))
+	}
+
+	fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(v.encode_utf8(&mut [0; 4]))
+	}
+
+	fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+		Ok(v.to_owned())
+	}
+
+	fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Self::Error> {
+		Err(SerializeError::InvalidKey("bytes"))
+	}
+
+	fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+		Err(SerializeError::InvalidKey("option"))
+	}
+
+	fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok, Self::Error>
+	where
+		T: serde::Serialize,
+	{
+		Err(SerializeError::InvalidKey("option"))
+	}
+
+	fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+		Err(SerializeError::InvalidKey("unit"))
+	}
+
+	fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+		Err(SerializeError::InvalidKey("unit struct"))
+	}
+
+	fn serialize_unit_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		variant: &'static str,
+	) -> Result<Self::Ok, Self::Error> {
+		self.serialize_str(variant)
+	}
+
+	fn serialize_newtype_struct<T: ?Sized>(
+		self,
+		_name: &'static str,
+		value: &T,
+	) -> Result<Self::Ok, Self::Error>
+	where
+		T: serde::Serialize,
+	{
+		value.serialize(self)
+	}
+
+	fn serialize_newtype_variant<T: ?Sized>(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_value: &T,
+	) -> Result<Self::Ok, Self::Error>
+	where
+		T: serde::Serialize,
+	{
+		Err(SerializeError::InvalidKey("newtype variant"))
+	}
+
+	fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+		Err(SerializeError::InvalidKey("array"))
+	}
+
+	fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+		Err(SerializeError::InvalidKey("tuple"))
+	}
+
+	fn serialize_tuple_struct(
+		self,
+		_name: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeTupleStruct, Self::Error> {
+		Err(SerializeError::InvalidKey("tuple struct"))
+	}
+
+	fn serialize_tuple_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeTupleVariant, Self::Error> {
+		Err(SerializeError::InvalidKey("tuple variant"))
+	}
+
+	fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+		Err(SerializeError::InvalidKey("map"))
+	}
+
+	fn serialize_struct(
+		self,
+		_name: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeStruct, Self::Error> {
+		Err(SerializeError::InvalidKey("struct"))
+	}
+
+	fn serialize_struct_variant(
+		self,
+		_name: &'static str,
+		_variant_index: u32,
+		_variant: &'static str,
+		_len: usize,
+	) -> Result<Self::SerializeStructVariant, Self::Error> {
+		Err(SerializeError::InvalidKey("struct variant"))
 	}
 }
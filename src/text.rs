@@ -0,0 +1,448 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! A small text notation for [Message] trees, for writing fixtures and
+//! golden files by hand.
+//!
+//! ```text
+//! // Comments run to the end of the line.
+//! {
+//!     "name": "main",           // string
+//!     "offset": -1,             // int64
+//!     "port": 80u,              // uint64
+//!     "ratio": 0.5,             // double, also 1e20, NaN, inf and -inf
+//!     "key": <00abff>,          // data, in hex
+//!     "session": uuid(00010203-0405-0607-0809-0a0b0c0d0e0f),
+//!     "parent": null,
+//!     "enabled": true,
+//!     "sources": [1, 2u, "three"],
+//! }
+//! ```
+//!
+//! Strings support the escapes `\"`, `\\`, `\n`, `\r`, `\t`, `\u{...}` for
+//! any character and `\xNN` for a raw byte, so strings that aren't valid UTF-8
+//! can be written too. Trailing commas are allowed.
+//!
+//! [print] writes every message this crate can produce in a form [parse] reads
+//! back into an identical message. Other message kinds are printed as
+//! `invalid`, which can't be parsed.
+
+use crate::{
+	error::{ParseError, ParseErrorKind},
+	Uuid,
+};
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	convert::TryFrom,
+	ffi::{CStr, CString},
+	fmt::Write,
+};
+use xpc_connection::Message;
+
+/// How deeply arrays and dictionaries may be nested before [parse] gives up.
+const MAX_DEPTH: usize = 128;
+
+/// Prints a message in the text notation, one array element or dictionary
+/// entry per line, with dictionary keys sorted.
+pub fn print(message: &Message) -> String {
+	let mut out = String::new();
+	write_message(&mut out, message, 0);
+	out
+}
+
+fn indent(out: &mut String, depth: usize) {
+	for _ in 0..depth {
+		out.push('\t');
+	}
+}
+
+fn write_message(out: &mut String, message: &Message, depth: usize) {
+	match message {
+		Message::Null => out.push_str("null"),
+		Message::Bool(v) => out.push_str(if *v { "true" } else { "false" }),
+		Message::Int64(v) => {
+			let _ = write!(out, "{}", v);
+		}
+		Message::Uint64(v) => {
+			let _ = write!(out, "{}u", v);
+		}
+		Message::Double(v) => {
+			let _ = write!(out, "{:?}", v);
+		}
+		Message::String(v) => write_string(out, v),
+		Message::Data(v) => {
+			out.push('<');
+			for byte in v {
+				let _ = write!(out, "{:02x}", byte);
+			}
+			out.push('>');
+		}
+		Message::Array(v) if v.is_empty() => out.push_str("[]"),
+		Message::Array(v) => {
+			out.push('[');
+			for element in v {
+				out.push('\n');
+				indent(out, depth + 1);
+				write_message(out, element, depth + 1);
+				out.push(',');
+			}
+			out.push('\n');
+			indent(out, depth);
+			out.push(']');
+		}
+		Message::Dictionary(v) if v.is_empty() => out.push_str("{}"),
+		Message::Dictionary(v) => {
+			let mut entries = v.iter().collect::<Vec<_>>();
+			entries.sort_by_key(|(key, _)| *key);
+			out.push('{');
+			for (key, value) in entries {
+				out.push('\n');
+				indent(out, depth + 1);
+				write_string(out, key);
+				out.push_str(": ");
+				write_message(out, value, depth + 1);
+				out.push(',');
+			}
+			out.push('\n');
+			indent(out, depth);
+			out.push('}');
+		}
+		Message::Uuid(v) => match Uuid::try_from(v.as_slice()) {
+			Ok(uuid) => {
+				let _ = write!(out, "uuid({})", uuid);
+			}
+			Err(_) => out.push_str("invalid"),
+		},
+		_ => out.push_str("invalid"),
+	}
+}
+
+fn write_string(out: &mut String, string: &CStr) {
+	out.push('"');
+	let mut bytes = string.to_bytes();
+	while !bytes.is_empty() {
+		let (valid, invalid) = match std::str::from_utf8(bytes) {
+			Ok(valid) => (valid, 0),
+			Err(error) => (
+				std::str::from_utf8(&bytes[..error.valid_up_to()]).unwrap_or_default(),
+				error
+					.error_len()
+					.unwrap_or(bytes.len() - error.valid_up_to()),
+			),
+		};
+		for c in valid.chars() {
+			match c {
+				'"' => out.push_str("\\\""),
+				'\\' => out.push_str("\\\\"),
+				'\n' => out.push_str("\\n"),
+				'\r' => out.push_str("\\r"),
+				'\t' => out.push_str("\\t"),
+				c if c.is_control() => {
+					let _ = write!(out, "\\u{{{:x}}}", c as u32);
+				}
+				c => out.push(c),
+			}
+		}
+		bytes = &bytes[valid.len()..];
+		for byte in &bytes[..invalid] {
+			let _ = write!(out, "\\x{:02x}", byte);
+		}
+		bytes = &bytes[invalid..];
+	}
+	out.push('"');
+}
+
+/// Parses a message written in the text notation.
+pub fn parse(input: &str) -> Result<Message, ParseError> {
+	let mut parser = Parser { input, position: 0 };
+	let message = parser.message(0)?;
+	parser.skip_whitespace();
+	if parser.position < input.len() {
+		return Err(parser.error(ParseErrorKind::TrailingCharacters));
+	}
+	Ok(message)
+}
+
+struct Parser<'a> {
+	input: &'a str,
+	position: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn error(&self, kind: ParseErrorKind) -> ParseError {
+		self.error_at(self.position, kind)
+	}
+
+	fn error_at(&self, position: usize, kind: ParseErrorKind) -> ParseError {
+		let before = &self.input[..position];
+		let line = before.matches('\n').count() + 1;
+		let column = before[before.rfind('\n').map_or(0, |i| i + 1)..]
+			.chars()
+			.count() + 1;
+		ParseError::new(line, column, kind)
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.input[self.position..].chars().next()
+	}
+
+	fn bump(&mut self) -> Option<char> {
+		let c = self.peek()?;
+		self.position += c.len_utf8();
+		Some(c)
+	}
+
+	fn skip_whitespace(&mut self) {
+		loop {
+			let rest = &self.input[self.position..];
+			if rest.starts_with("//") {
+				self.position += rest.find('\n').unwrap_or(rest.len());
+			} else if let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+				self.position += c.len_utf8();
+			} else {
+				return;
+			}
+		}
+	}
+
+	fn expect(&mut self, expected: char) -> Result<(), ParseError> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some(c) if c == expected => {
+				self.bump();
+				Ok(())
+			}
+			Some(c) => Err(self.error(ParseErrorKind::UnexpectedChar(c))),
+			None => Err(self.error(ParseErrorKind::UnexpectedEof)),
+		}
+	}
+
+	/// Consumes the separator after an element, returning `true` if the
+	/// container is closed by `close`.
+	fn separator(&mut self, close: char) -> Result<bool, ParseError> {
+		self.skip_whitespace();
+		match self.peek() {
+			Some(',') => {
+				self.bump();
+				self.skip_whitespace();
+				if self.peek() == Some(close) {
+					self.bump();
+					return Ok(true);
+				}
+				Ok(false)
+			}
+			Some(c) if c == close => {
+				self.bump();
+				Ok(true)
+			}
+			Some(c) => Err(self.error(ParseErrorKind::UnexpectedChar(c))),
+			None => Err(self.error(ParseErrorKind::UnexpectedEof)),
+		}
+	}
+
+	fn message(&mut self, depth: usize) -> Result<Message, ParseError> {
+		self.skip_whitespace();
+		match self.peek() {
+			None => Err(self.error(ParseErrorKind::UnexpectedEof)),
+			Some('[') | Some('{') if depth >= MAX_DEPTH => {
+				Err(self.error(ParseErrorKind::RecursionLimitExceeded))
+			}
+			Some('[') => {
+				self.bump();
+				self.array(depth)
+			}
+			Some('{') => {
+				self.bump();
+				self.dictionary(depth)
+			}
+			Some('"') => Ok(Message::String(self.string()?)),
+			Some('<') => self.data(),
+			Some(c) if c == '-' || c.is_ascii_alphanumeric() => self.word(),
+			Some(c) => Err(self.error(ParseErrorKind::UnexpectedChar(c))),
+		}
+	}
+
+	fn array(&mut self, depth: usize) -> Result<Message, ParseError> {
+		let mut array = Vec::new();
+		self.skip_whitespace();
+		if self.peek() == Some(']') {
+			self.bump();
+			return Ok(Message::Array(array));
+		}
+		loop {
+			array.push(self.message(depth + 1)?);
+			if self.separator(']')? {
+				return Ok(Message::Array(array));
+			}
+		}
+	}
+
+	fn dictionary(&mut self, depth: usize) -> Result<Message, ParseError> {
+		let mut map = HashMap::new();
+		self.skip_whitespace();
+		if self.peek() == Some('}') {
+			self.bump();
+			return Ok(Message::Dictionary(map));
+		}
+		loop {
+			self.skip_whitespace();
+			let start = self.position;
+			match self.peek() {
+				Some('"') => {}
+				Some(c) => return Err(self.error(ParseErrorKind::UnexpectedChar(c))),
+				None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+			}
+			let key = self.string()?;
+			self.expect(':')?;
+			let value = self.message(depth + 1)?;
+			match map.entry(key) {
+				Entry::Occupied(entry) => {
+					let key = entry.key().to_string_lossy().into_owned();
+					return Err(self.error_at(start, ParseErrorKind::DuplicateKey(key)));
+				}
+				Entry::Vacant(entry) => {
+					entry.insert(value);
+				}
+			}
+			if self.separator('}')? {
+				return Ok(Message::Dictionary(map));
+			}
+		}
+	}
+
+	fn string(&mut self) -> Result<CString, ParseError> {
+		let start = self.position;
+		self.bump();
+		let mut bytes = Vec::new();
+		loop {
+			let escape = self.position;
+			match self.bump() {
+				None => return Err(self.error(ParseErrorKind::UnexpectedEof)),
+				Some('"') => break,
+				Some('\\') => match self.bump() {
+					Some('"') => bytes.push(b'"'),
+					Some('\\') => bytes.push(b'\\'),
+					Some('n') => bytes.push(b'\n'),
+					Some('r') => bytes.push(b'\r'),
+					Some('t') => bytes.push(b'\t'),
+					Some('x') => {
+						let hex = self.input.get(self.position..self.position + 2);
+						let byte = hex
+							.filter(|hex| is_hex(hex))
+							.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+						match byte {
+							Some(byte) => {
+								self.position += 2;
+								bytes.push(byte);
+							}
+							None => {
+								return Err(self.error_at(escape, ParseErrorKind::InvalidEscape))
+							}
+						}
+					}
+					Some('u') => {
+						let rest = &self.input[self.position..];
+						let c = rest
+							.strip_prefix('{')
+							.and_then(|rest| rest.split_once('}'))
+							.filter(|(hex, _)| is_hex(hex))
+							.and_then(|(hex, _)| {
+								u32::from_str_radix(hex, 16).ok().map(|c| (hex, c))
+							})
+							.and_then(|(hex, c)| Some((hex, std::char::from_u32(c)?)));
+						match c {
+							Some((hex, c)) => {
+								self.position += hex.len() + 2;
+								bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+							}
+							None => {
+								return Err(self.error_at(escape, ParseErrorKind::InvalidEscape))
+							}
+						}
+					}
+					_ => return Err(self.error_at(escape, ParseErrorKind::InvalidEscape)),
+				},
+				Some(c) => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+			}
+		}
+		CString::new(bytes).map_err(|_| self.error_at(start, ParseErrorKind::NulInString))
+	}
+
+	fn data(&mut self) -> Result<Message, ParseError> {
+		let start = self.position;
+		self.bump();
+		let rest = &self.input[self.position..];
+		let hex = match rest.find('>') {
+			Some(end) => &rest[..end],
+			None => {
+				self.position = self.input.len();
+				return Err(self.error(ParseErrorKind::UnexpectedEof));
+			}
+		};
+		if hex.len() % 2 != 0 || !(hex.is_empty() || is_hex(hex)) {
+			return Err(self.error_at(start, ParseErrorKind::InvalidData));
+		}
+		let data = (0..hex.len())
+			.step_by(2)
+			.map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+			.collect::<Result<Vec<_>, _>>()
+			.map_err(|_| self.error_at(start, ParseErrorKind::InvalidData))?;
+		self.position += hex.len() + 1;
+		Ok(Message::Data(data))
+	}
+
+	/// Parses a keyword or a number.
+	fn word(&mut self) -> Result<Message, ParseError> {
+		let start = self.position;
+		let rest = &self.input[start..];
+		let end = rest
+			.find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '+' || c == '-'))
+			.unwrap_or(rest.len());
+		let word = &rest[..end];
+		self.position += end;
+		let message = match word {
+			"null" => Some(Message::Null),
+			"true" => Some(Message::Bool(true)),
+			"false" => Some(Message::Bool(false)),
+			"NaN" | "inf" | "-inf" => word.parse().ok().map(Message::Double),
+			"uuid" if self.peek() == Some('(') => return self.uuid(start),
+			_ => match word.strip_suffix('u') {
+				Some(unsigned) => unsigned.parse().ok().map(Message::Uint64),
+				None if word.contains(&['.', 'e', 'E'][..]) => {
+					word.parse().ok().map(Message::Double)
+				}
+				None => word.parse().ok().map(Message::Int64),
+			},
+		};
+		message.ok_or_else(|| self.error_at(start, ParseErrorKind::InvalidWord(word.to_owned())))
+	}
+
+	/// Parses the parenthesised part of `uuid(...)`.
+	fn uuid(&mut self, start: usize) -> Result<Message, ParseError> {
+		let rest = &self.input[self.position..];
+		let end = match rest.find(')') {
+			Some(end) => end,
+			None => {
+				self.position = self.input.len();
+				return Err(self.error(ParseErrorKind::UnexpectedEof));
+			}
+		};
+		let uuid = rest[1..end].parse::<Uuid>().map_err(|_| {
+			let word = self.input[start..self.position + end + 1].to_owned();
+			self.error_at(start, ParseErrorKind::InvalidWord(word))
+		})?;
+		self.position += end + 1;
+		Ok(Message::Uuid(uuid.as_bytes().to_vec()))
+	}
+}
+
+/// Whether `s` is one or more hex digits. `from_str_radix` alone would also
+/// accept a leading `+`.
+fn is_hex(s: &str) -> bool {
+	!s.is_empty() && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
@@ -0,0 +1,188 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::error::InvalidUuid;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{convert::TryFrom, fmt, str::FromStr};
+
+/// The newtype struct name [Uuid] serializes with, so the serializer and
+/// deserializer can map it to [Message::Uuid](xpc_connection::Message::Uuid).
+pub(crate) const UUID_NAME: &str = "$xpc_serde::Uuid";
+
+/// A UUID, serialized as the native XPC uuid type that `xpc_dictionary_get_uuid`
+/// reads.
+///
+/// By default only [Message::Uuid](xpc_connection::Message::Uuid) is accepted
+/// when deserializing. With [UuidCoercion::Lenient](crate::UuidCoercion),
+/// 16 bytes of data and hyphenated strings are accepted too. Other serde
+/// formats see a hyphenated string, or 16 bytes if they aren't human-readable.
+///
+/// With the `uuid` feature, it converts to and from [uuid::Uuid](::uuid::Uuid).
+///
+/// ```
+/// use xpc_serde::Uuid;
+///
+/// let uuid: Uuid = "00010203-0405-0607-0809-0a0b0c0d0e0f".parse().unwrap();
+/// assert_eq!(uuid.as_bytes()[15], 0x0f);
+/// assert_eq!(uuid.to_string(), "00010203-0405-0607-0809-0a0b0c0d0e0f");
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Uuid([u8; 16]);
+
+impl Uuid {
+	/// The nil UUID, with every bit set to zero.
+	pub const NIL: Uuid = Uuid([0; 16]);
+
+	pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+		Self(bytes)
+	}
+
+	pub fn as_bytes(&self) -> &[u8; 16] {
+		&self.0
+	}
+}
+
+impl fmt::Display for Uuid {
+	/// Writes the UUID in lowercase hyphenated form.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (i, byte) in self.0.iter().enumerate() {
+			if i == 4 || i == 6 || i == 8 || i == 10 {
+				f.write_str("-")?;
+			}
+			write!(f, "{:02x}", byte)?;
+		}
+		Ok(())
+	}
+}
+
+impl fmt::Debug for Uuid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "Uuid({})", self)
+	}
+}
+
+impl FromStr for Uuid {
+	type Err = InvalidUuid;
+
+	/// Parses a hyphenated UUID, or 32 hex digits without hyphens.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let hex = match s.len() {
+			36 => {
+				let hyphens = [8, 13, 18, 23];
+				if !hyphens.iter().all(|i| s.as_bytes()[*i] == b'-') {
+					return Err(InvalidUuid);
+				}
+				s.replace('-', "")
+			}
+			32 => s.to_owned(),
+			_ => return Err(InvalidUuid),
+		};
+		if hex.len() != 32 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+			return Err(InvalidUuid);
+		}
+		let mut bytes = [0; 16];
+		for (i, byte) in bytes.iter_mut().enumerate() {
+			*byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| InvalidUuid)?;
+		}
+		Ok(Self(bytes))
+	}
+}
+
+impl TryFrom<&[u8]> for Uuid {
+	type Error = InvalidUuid;
+
+	fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+		<[u8; 16]>::try_from(bytes)
+			.map(Self)
+			.map_err(|_| InvalidUuid)
+	}
+}
+
+#[cfg(feature = "uuid")]
+impl From<::uuid::Uuid> for Uuid {
+	fn from(uuid: ::uuid::Uuid) -> Self {
+		Self(*uuid.as_bytes())
+	}
+}
+
+#[cfg(feature = "uuid")]
+impl From<Uuid> for ::uuid::Uuid {
+	fn from(uuid: Uuid) -> Self {
+		::uuid::Uuid::from_bytes(uuid.0)
+	}
+}
+
+impl Serialize for Uuid {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		serializer.serialize_newtype_struct(UUID_NAME, &Repr(self))
+	}
+}
+
+/// How the contents of a [Uuid] are serialized inside its newtype struct.
+struct Repr<'a>(&'a Uuid);
+
+impl Serialize for Repr<'_> {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		if serializer.is_human_readable() {
+			serializer.collect_str(self.0)
+		} else {
+			serializer.serialize_bytes(&self.0 .0)
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for Uuid {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_newtype_struct(UUID_NAME, UuidVisitor)
+	}
+}
+
+struct UuidVisitor;
+
+impl<'de> de::Visitor<'de> for UuidVisitor {
+	type Value = Uuid;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a UUID")
+	}
+
+	fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		if deserializer.is_human_readable() {
+			deserializer.deserialize_str(self)
+		} else {
+			deserializer.deserialize_bytes(self)
+		}
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		v.parse()
+			.map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+	}
+
+	fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+	where
+		E: de::Error,
+	{
+		Uuid::try_from(v).map_err(|_| E::invalid_length(v.len(), &self))
+	}
+}
@@ -0,0 +1,414 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+use crate::{
+	error::{DeserializeError, SerializeError, ValueError},
+	Uuid,
+};
+use serde::{
+	de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor},
+	ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer},
+};
+use std::{collections::HashMap, convert::TryFrom, ffi::CString, fmt, ops};
+use xpc_connection::Message;
+
+/// A dynamically typed XPC value, holding any of the message kinds this
+/// crate can serialize to and deserialize from.
+///
+/// Strings and dictionary keys are kept as [CString]s, exactly as they
+/// appear in a [Message], so converting between the two never loses
+/// anything. Messages [Value] can't hold, clients and errors, fail to
+/// convert.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+	Null,
+	Bool(bool),
+	Int64(i64),
+	Uint64(u64),
+	Double(f64),
+	String(CString),
+	Data(Vec<u8>),
+	Uuid(Uuid),
+	Array(Vec<Value>),
+	Dictionary(HashMap<CString, Value>),
+}
+
+static NULL: Value = Value::Null;
+
+impl Value {
+	/// Looks up a dictionary key or an array index, returning [None] if the
+	/// value is not a container of the right kind or the entry is missing.
+	pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+		index.index_into(self)
+	}
+
+	/// Looks up a nested value by a JSON Pointer (RFC 6901) such as
+	/// `/settings/sources/3/port`.
+	pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+		if pointer.is_empty() {
+			return Some(self);
+		}
+		if !pointer.starts_with('/') {
+			return None;
+		}
+		pointer
+			.split('/')
+			.skip(1)
+			.map(|token| token.replace("~1", "/").replace("~0", "~"))
+			.try_fold(self, |target, token| match target {
+				Value::Dictionary(_) => target.get(token.as_str()),
+				Value::Array(_) => target.get(token.parse::<usize>().ok()?),
+				_ => None,
+			})
+	}
+
+	pub fn is_null(&self) -> bool {
+		matches!(self, Value::Null)
+	}
+
+	pub fn as_bool(&self) -> Option<bool> {
+		match self {
+			Value::Bool(v) => Some(*v),
+			_ => None,
+		}
+	}
+
+	/// Returns the value as an [i64] if it is an integer that fits.
+	pub fn as_i64(&self) -> Option<i64> {
+		match self {
+			Value::Int64(v) => Some(*v),
+			Value::Uint64(v) => i64::try_from(*v).ok(),
+			_ => None,
+		}
+	}
+
+	/// Returns the value as a [u64] if it is an integer that fits.
+	pub fn as_u64(&self) -> Option<u64> {
+		match self {
+			Value::Int64(v) => u64::try_from(*v).ok(),
+			Value::Uint64(v) => Some(*v),
+			_ => None,
+		}
+	}
+
+	pub fn as_f64(&self) -> Option<f64> {
+		match self {
+			Value::Double(v) => Some(*v),
+			_ => None,
+		}
+	}
+
+	/// Returns the value as a [str] if it is a string holding valid UTF-8.
+	pub fn as_str(&self) -> Option<&str> {
+		match self {
+			Value::String(v) => v.to_str().ok(),
+			_ => None,
+		}
+	}
+
+	pub fn as_bytes(&self) -> Option<&[u8]> {
+		match self {
+			Value::Data(v) => Some(v),
+			_ => None,
+		}
+	}
+
+	pub fn as_uuid(&self) -> Option<Uuid> {
+		match self {
+			Value::Uuid(v) => Some(*v),
+			_ => None,
+		}
+	}
+
+	pub fn as_array(&self) -> Option<&Vec<Value>> {
+		match self {
+			Value::Array(v) => Some(v),
+			_ => None,
+		}
+	}
+
+	pub fn as_dictionary(&self) -> Option<&HashMap<CString, Value>> {
+		match self {
+			Value::Dictionary(v) => Some(v),
+			_ => None,
+		}
+	}
+}
+
+mod private {
+	pub trait Sealed {}
+	impl Sealed for usize {}
+	impl Sealed for str {}
+	impl Sealed for String {}
+	impl<T: ?Sized + Sealed> Sealed for &T {}
+}
+
+/// Types that can index into a [Value]: [str] and [String] look up
+/// dictionary keys, [usize] looks up array elements.
+pub trait ValueIndex: private::Sealed {
+	#[doc(hidden)]
+	fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>;
+}
+
+impl ValueIndex for usize {
+	fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+		match value {
+			Value::Array(array) => array.get(*self),
+			_ => None,
+		}
+	}
+}
+
+impl ValueIndex for str {
+	fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+		match value {
+			Value::Dictionary(map) => map.get(CString::new(self).ok()?.as_c_str()),
+			_ => None,
+		}
+	}
+}
+
+impl ValueIndex for String {
+	fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+		self.as_str().index_into(value)
+	}
+}
+
+impl<T: ?Sized + ValueIndex> ValueIndex for &T {
+	fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+		(**self).index_into(value)
+	}
+}
+
+/// Indexing a [Value] never panics: missing entries, and indexing into
+/// anything but a container, yield [Value::Null].
+impl<I: ValueIndex> ops::Index<I> for Value {
+	type Output = Value;
+
+	fn index(&self, index: I) -> &Value {
+		index.index_into(self).unwrap_or(&NULL)
+	}
+}
+
+/// Converts a [Message] into a [Value]. Clients, errors, and UUIDs that
+/// aren't 16 bytes long can't be held by a [Value], and fail to convert.
+impl TryFrom<Message> for Value {
+	type Error = ValueError;
+
+	fn try_from(message: Message) -> Result<Self, Self::Error> {
+		Ok(match message {
+			Message::Null => Value::Null,
+			Message::Bool(v) => Value::Bool(v),
+			Message::Int64(v) => Value::Int64(v),
+			Message::Uint64(v) => Value::Uint64(v),
+			Message::Double(v) => Value::Double(v),
+			Message::String(v) => Value::String(v),
+			Message::Data(v) => Value::Data(v),
+			Message::Uuid(v) => Value::Uuid(
+				Uuid::try_from(v.as_slice()).map_err(|_| ValueError::InvalidUuid(v.len()))?,
+			),
+			Message::Array(v) => Value::Array(
+				v.into_iter()
+					.map(Value::try_from)
+					.collect::<Result<_, _>>()?,
+			),
+			Message::Dictionary(v) => Value::Dictionary(
+				v.into_iter()
+					.map(|(key, value)| Ok((key, Value::try_from(value)?)))
+					.collect::<Result<_, ValueError>>()?,
+			),
+			message => {
+				return Err(ValueError::Unsupported(crate::xpc_message_to_type(
+					&message,
+				)))
+			}
+		})
+	}
+}
+
+/// Converts a [Value] back into a [Message]. This never fails, so the
+/// blanket `TryInto<Message>` implementation can be used as well.
+impl From<Value> for Message {
+	fn from(value: Value) -> Self {
+		match value {
+			Value::Null => Message::Null,
+			Value::Bool(v) => Message::Bool(v),
+			Value::Int64(v) => Message::Int64(v),
+			Value::Uint64(v) => Message::Uint64(v),
+			Value::Double(v) => Message::Double(v),
+			Value::String(v) => Message::String(v),
+			Value::Data(v) => Message::Data(v),
+			Value::Uuid(v) => Message::Uuid(v.as_bytes().to_vec()),
+			Value::Array(v) => Message::Array(v.into_iter().map(Message::from).collect()),
+			Value::Dictionary(v) => Message::Dictionary(
+				v.into_iter()
+					.map(|(key, value)| (key, Message::from(value)))
+					.collect(),
+			),
+		}
+	}
+}
+
+impl Serialize for Value {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+	where
+		S: Serializer,
+	{
+		match self {
+			Value::Null => serializer.serialize_unit(),
+			Value::Bool(v) => serializer.serialize_bool(*v),
+			Value::Int64(v) => serializer.serialize_i64(*v),
+			Value::Uint64(v) => serializer.serialize_u64(*v),
+			Value::Double(v) => serializer.serialize_f64(*v),
+			Value::String(v) => serializer.serialize_str(v.to_str().map_err(ser::Error::custom)?),
+			Value::Data(v) => serializer.serialize_bytes(v),
+			Value::Uuid(v) => v.serialize(serializer),
+			Value::Array(v) => {
+				let mut seq = serializer.serialize_seq(Some(v.len()))?;
+				for element in v {
+					seq.serialize_element(element)?;
+				}
+				seq.end()
+			}
+			Value::Dictionary(v) => {
+				let mut map = serializer.serialize_map(Some(v.len()))?;
+				for (key, value) in v {
+					map.serialize_entry(key.to_str().map_err(ser::Error::custom)?, value)?;
+				}
+				map.end()
+			}
+		}
+	}
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+	type Value = Value;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("any XPC value")
+	}
+
+	fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+		Ok(Value::Bool(v))
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+		Ok(Value::Int64(v))
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+		Ok(Value::Uint64(v))
+	}
+
+	fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+		Ok(Value::Double(v))
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Value, E>
+	where
+		E: de::Error,
+	{
+		self.visit_string(v.to_owned())
+	}
+
+	fn visit_string<E>(self, v: String) -> Result<Value, E>
+	where
+		E: de::Error,
+	{
+		CString::new(v)
+			.map(Value::String)
+			.map_err(de::Error::custom)
+	}
+
+	fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+		Ok(Value::Data(v.to_vec()))
+	}
+
+	fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+		Ok(Value::Data(v))
+	}
+
+	fn visit_none<E>(self) -> Result<Value, E> {
+		Ok(Value::Null)
+	}
+
+	fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Value::deserialize(deserializer)
+	}
+
+	fn visit_unit<E>(self) -> Result<Value, E> {
+		Ok(Value::Null)
+	}
+
+	/// Deserializing a [Message::Uuid] as any type visits a newtype struct
+	/// holding its bytes.
+	fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		Uuid::deserialize(deserializer).map(Value::Uuid)
+	}
+
+	fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+	where
+		A: SeqAccess<'de>,
+	{
+		let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+		while let Some(element) = seq.next_element()? {
+			array.push(element);
+		}
+		Ok(Value::Array(array))
+	}
+
+	fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+	where
+		A: MapAccess<'de>,
+	{
+		let mut dictionary = HashMap::with_capacity(map.size_hint().unwrap_or(0));
+		while let Some((key, value)) = map.next_entry::<String, Value>()? {
+			dictionary.insert(CString::new(key).map_err(de::Error::custom)?, value);
+		}
+		Ok(Value::Dictionary(dictionary))
+	}
+}
+
+impl<'de> Deserialize<'de> for Value {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_any(ValueVisitor)
+	}
+}
+
+/// Serializes a value into a [Value], the same way [serialize](crate::serialize)
+/// would serialize it into a [Message].
+pub fn to_value<T>(v: &T) -> Result<Value, SerializeError>
+where
+	T: Serialize + ?Sized,
+{
+	crate::SerializerConfig::default()
+		.serialize(v)
+		.and_then(|message| {
+			Value::try_from(message).map_err(|error| SerializeError::Custom(error.to_string()))
+		})
+}
+
+/// Deserializes a value from a [Value], the same way
+/// [deserialize](crate::deserialize) would deserialize it from a [Message].
+pub fn from_value<T>(value: Value) -> Result<T, DeserializeError>
+where
+	T: de::DeserializeOwned,
+{
+	crate::deserialize(Message::from(value))
+}
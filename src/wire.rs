@@ -0,0 +1,391 @@
+/*
+	Copyright (c) 2021 Lucy <lucy@absolucy.moe>
+
+	This Source Code Form is subject to the terms of the Mozilla Public
+	License, v. 2.0. If a copy of the MPL was not distributed with this
+	file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! A pure-Rust encoder and decoder for the binary format libxpc puts on the
+//! wire, for reading traffic captures and for testing without libxpc.
+//!
+//! An encoded message starts with the [MAGIC] number and the format [VERSION],
+//! followed by a single object. Every object starts with its type, and every
+//! record is padded to a multiple of 4 bytes. All integers are little-endian.
+//!
+//! | Type         | Id       | Payload                                                |
+//! |--------------|----------|--------------------------------------------------------|
+//! | `null`       | `0x1000` | nothing                                                |
+//! | `bool`       | `0x2000` | `u32`, 0 or 1                                          |
+//! | `int64`      | `0x3000` | `i64`                                                  |
+//! | `uint64`     | `0x4000` | `u64`                                                  |
+//! | `double`     | `0x5000` | `f64`                                                  |
+//! | `date`       | `0x7000` | `i64` nanoseconds since the Unix epoch                 |
+//! | `data`       | `0x8000` | `u32` length, then the bytes                           |
+//! | `string`     | `0x9000` | `u32` length including the NUL, then the bytes and NUL |
+//! | `uuid`       | `0xa000` | 16 bytes                                               |
+//! | `array`      | `0xe000` | `u32` length of the rest, `u32` count, then objects    |
+//! | `dictionary` | `0xf000` | `u32` length of the rest, `u32` count, then entries    |
+//!
+//! Dictionary entries are a NUL-terminated, padded key followed by an object.
+//! Dictionaries are encoded with their keys sorted, so encoding is
+//! deterministic.
+//!
+//! Dates decode to a [Message::Int64] of nanoseconds, since [Message] has no
+//! date type. Objects that carry kernel resources, like file descriptors and
+//! mach ports, only make sense alongside the mach message that carried them,
+//! and can't be decoded.
+
+use crate::{error::WireError, xpc_message_to_type};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+	collections::{hash_map::Entry, HashMap},
+	convert::TryFrom,
+	ffi::{CStr, CString},
+};
+use xpc_connection::Message;
+
+/// The magic number every encoded message starts with.
+pub const MAGIC: u32 = 0x42133742;
+/// The version of the format this module reads and writes.
+pub const VERSION: u32 = 5;
+
+/// The quiet NaN every NaN is written as in canonical form.
+const CANONICAL_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+/// How deeply arrays and dictionaries may be nested before decoding gives up.
+const MAX_DEPTH: usize = 128;
+
+const NULL: u32 = 0x1000;
+const BOOL: u32 = 0x2000;
+const INT64: u32 = 0x3000;
+const UINT64: u32 = 0x4000;
+const DOUBLE: u32 = 0x5000;
+const DATE: u32 = 0x7000;
+const DATA: u32 = 0x8000;
+const STRING: u32 = 0x9000;
+const UUID: u32 = 0xa000;
+const ARRAY: u32 = 0xe000;
+const DICTIONARY: u32 = 0xf000;
+
+/// Names the known object types this module can't decode.
+fn unsupported_type(kind: u32) -> Option<&'static str> {
+	Some(match kind {
+		0x6000 => "pointer",
+		0xb000 => "fd",
+		0xc000 => "shmem",
+		0xd000 => "mach_send",
+		0x11000 => "connection",
+		0x12000 => "endpoint",
+		0x13000 => "serializer",
+		0x14000 => "pipe",
+		0x15000 => "mach_recv",
+		0x16000 => "bundle",
+		0x17000 => "service",
+		0x18000 => "service_instance",
+		0x19000 => "activity",
+		0x1a000 => "file_transfer",
+		_ => return None,
+	})
+}
+
+/// Encodes a message in the wire format, header included.
+pub fn encode(message: &Message) -> Result<Vec<u8>, WireError> {
+	encode_with(message, false)
+}
+
+/// Encodes a message, normalizing integers and doubles if `canonical` is set.
+/// See [canonical](crate::canonical) for what changes.
+pub(crate) fn encode_with(message: &Message, canonical: bool) -> Result<Vec<u8>, WireError> {
+	let mut out = Vec::new();
+	write_u32(&mut out, MAGIC);
+	write_u32(&mut out, VERSION);
+	encode_object(&mut out, message, canonical)?;
+	Ok(out)
+}
+
+/// Serializes a value straight to the wire format.
+pub fn to_vec<T>(v: &T) -> Result<Vec<u8>, WireError>
+where
+	T: Serialize,
+{
+	encode(&crate::serialize(v)?)
+}
+
+fn write_u32(out: &mut Vec<u8>, v: u32) {
+	out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn write_length(out: &mut Vec<u8>, length: usize, kind: &'static str) -> Result<(), WireError> {
+	write_u32(
+		out,
+		u32::try_from(length).map_err(|_| WireError::TooLarge(kind))?,
+	);
+	Ok(())
+}
+
+/// How many bytes of padding follow a record ending at `offset`.
+fn padding(offset: usize) -> usize {
+	(4 - offset % 4) % 4
+}
+
+fn pad(out: &mut Vec<u8>) {
+	out.resize(out.len() + padding(out.len()), 0);
+}
+
+fn write_cstr(out: &mut Vec<u8>, v: &CStr) {
+	out.extend_from_slice(v.to_bytes_with_nul());
+	pad(out);
+}
+
+fn encode_object(out: &mut Vec<u8>, message: &Message, canonical: bool) -> Result<(), WireError> {
+	match message {
+		Message::Null => write_u32(out, NULL),
+		Message::Bool(v) => {
+			write_u32(out, BOOL);
+			write_u32(out, *v as u32);
+		}
+		Message::Int64(v) if canonical && *v >= 0 => {
+			write_u32(out, UINT64);
+			out.extend_from_slice(&(*v as u64).to_le_bytes());
+		}
+		Message::Int64(v) => {
+			write_u32(out, INT64);
+			out.extend_from_slice(&v.to_le_bytes());
+		}
+		Message::Uint64(v) => {
+			write_u32(out, UINT64);
+			out.extend_from_slice(&v.to_le_bytes());
+		}
+		Message::Double(v) => {
+			let v = match *v {
+				v if canonical && v.is_nan() => f64::from_bits(CANONICAL_NAN),
+				// Turns -0.0 into 0.0.
+				v if canonical && v == 0.0 => 0.0,
+				v => v,
+			};
+			write_u32(out, DOUBLE);
+			out.extend_from_slice(&v.to_le_bytes());
+		}
+		Message::Data(v) => {
+			write_u32(out, DATA);
+			write_length(out, v.len(), "data")?;
+			out.extend_from_slice(v);
+			pad(out);
+		}
+		Message::String(v) => {
+			write_u32(out, STRING);
+			write_length(out, v.to_bytes_with_nul().len(), "string")?;
+			write_cstr(out, v);
+		}
+		Message::Uuid(v) => {
+			if v.len() != 16 {
+				return Err(WireError::InvalidUuid(v.len()));
+			}
+			write_u32(out, UUID);
+			out.extend_from_slice(v);
+		}
+		Message::Array(v) => {
+			write_u32(out, ARRAY);
+			encode_container(out, v.len(), "array", |out| {
+				v.iter()
+					.try_for_each(|message| encode_object(out, message, canonical))
+			})?;
+		}
+		Message::Dictionary(v) => {
+			let mut entries = v.iter().collect::<Vec<_>>();
+			entries.sort_by_key(|(key, _)| *key);
+			write_u32(out, DICTIONARY);
+			encode_container(out, entries.len(), "dictionary", |out| {
+				entries.iter().try_for_each(|(key, message)| {
+					write_cstr(out, key);
+					encode_object(out, message, canonical)
+				})
+			})?;
+		}
+		_ => return Err(WireError::Unsupported(xpc_message_to_type(message))),
+	}
+	Ok(())
+}
+
+/// Writes the length and count of an array or dictionary around its contents.
+fn encode_container<F>(
+	out: &mut Vec<u8>,
+	count: usize,
+	kind: &'static str,
+	contents: F,
+) -> Result<(), WireError>
+where
+	F: FnOnce(&mut Vec<u8>) -> Result<(), WireError>,
+{
+	let length_at = out.len();
+	write_u32(out, 0);
+	write_length(out, count, kind)?;
+	contents(out)?;
+	let length = u32::try_from(out.len() - length_at - 4).map_err(|_| WireError::TooLarge(kind))?;
+	out[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+	Ok(())
+}
+
+/// Decodes a message from the wire format, header included.
+pub fn decode(bytes: &[u8]) -> Result<Message, WireError> {
+	let mut reader = Reader { bytes, offset: 0 };
+	let magic = reader.u32()?;
+	if magic != MAGIC {
+		return Err(WireError::InvalidMagic(magic));
+	}
+	let version = reader.u32()?;
+	if version != VERSION {
+		return Err(WireError::UnsupportedVersion(version));
+	}
+	let message = reader.object(0)?;
+	if reader.offset < bytes.len() {
+		return Err(WireError::TrailingBytes(reader.offset));
+	}
+	Ok(message)
+}
+
+/// Deserializes a value straight from the wire format.
+pub fn from_slice<T>(bytes: &[u8]) -> Result<T, WireError>
+where
+	T: DeserializeOwned,
+{
+	Ok(crate::deserialize(decode(bytes)?)?)
+}
+
+struct Reader<'a> {
+	bytes: &'a [u8],
+	offset: usize,
+}
+
+impl<'a> Reader<'a> {
+	fn take(&mut self, length: usize) -> Result<&'a [u8], WireError> {
+		let end = self
+			.offset
+			.checked_add(length)
+			.filter(|end| *end <= self.bytes.len())
+			.ok_or(WireError::UnexpectedEof(self.offset))?;
+		let bytes = &self.bytes[self.offset..end];
+		self.offset = end;
+		Ok(bytes)
+	}
+
+	fn array<const N: usize>(&mut self) -> Result<[u8; N], WireError> {
+		let mut array = [0; N];
+		array.copy_from_slice(self.take(N)?);
+		Ok(array)
+	}
+
+	fn u32(&mut self) -> Result<u32, WireError> {
+		Ok(u32::from_le_bytes(self.array()?))
+	}
+
+	fn length(&mut self) -> Result<usize, WireError> {
+		Ok(self.u32()? as usize)
+	}
+
+	fn skip_padding(&mut self) -> Result<(), WireError> {
+		self.take(padding(self.offset))?;
+		Ok(())
+	}
+
+	/// Reads a NUL-terminated, padded dictionary key.
+	fn key(&mut self) -> Result<CString, WireError> {
+		let start = self.offset;
+		let length = self.bytes[start..]
+			.iter()
+			.position(|b| *b == 0)
+			.ok_or(WireError::UnexpectedEof(start))?;
+		let key = CString::new(self.take(length)?).map_err(|_| WireError::InvalidString(start))?;
+		self.take(1)?;
+		self.skip_padding()?;
+		Ok(key)
+	}
+
+	fn object(&mut self, depth: usize) -> Result<Message, WireError> {
+		let start = self.offset;
+		let kind = self.u32()?;
+		Ok(match kind {
+			NULL => Message::Null,
+			BOOL => Message::Bool(self.u32()? != 0),
+			INT64 | DATE => Message::Int64(i64::from_le_bytes(self.array()?)),
+			UINT64 => Message::Uint64(u64::from_le_bytes(self.array()?)),
+			DOUBLE => Message::Double(f64::from_le_bytes(self.array()?)),
+			DATA => {
+				let length = self.length()?;
+				let data = self.take(length)?.to_vec();
+				self.skip_padding()?;
+				Message::Data(data)
+			}
+			STRING => {
+				let length = self.length()?;
+				let bytes = self.take(length)?;
+				let string = CStr::from_bytes_with_nul(bytes)
+					.map_err(|_| WireError::InvalidString(start))?
+					.to_owned();
+				self.skip_padding()?;
+				Message::String(string)
+			}
+			UUID => Message::Uuid(self.take(16)?.to_vec()),
+			ARRAY | DICTIONARY if depth >= MAX_DEPTH => {
+				return Err(WireError::RecursionLimitExceeded(start))
+			}
+			ARRAY => {
+				let end = self.container_end(start)?;
+				let count = self.length()?;
+				let mut array = Vec::new();
+				for _ in 0..count {
+					array.push(self.object(depth + 1)?);
+				}
+				self.check_end(start, end)?;
+				Message::Array(array)
+			}
+			DICTIONARY => {
+				let end = self.container_end(start)?;
+				let count = self.length()?;
+				let mut map = HashMap::new();
+				for _ in 0..count {
+					let key_at = self.offset;
+					let key = self.key()?;
+					let value = self.object(depth + 1)?;
+					match map.entry(key) {
+						Entry::Occupied(_) => return Err(WireError::DuplicateKey(key_at)),
+						Entry::Vacant(entry) => {
+							entry.insert(value);
+						}
+					}
+				}
+				self.check_end(start, end)?;
+				Message::Dictionary(map)
+			}
+			kind => {
+				return Err(match unsupported_type(kind) {
+					Some(name) => WireError::UnsupportedType {
+						kind: name,
+						offset: start,
+					},
+					None => WireError::UnknownType {
+						kind,
+						offset: start,
+					},
+				})
+			}
+		})
+	}
+
+	/// Reads the length of a container, returning the offset it ends at.
+	fn container_end(&mut self, start: usize) -> Result<usize, WireError> {
+		let length = self.length()?;
+		self.offset
+			.checked_add(length)
+			.filter(|end| *end <= self.bytes.len())
+			.ok_or(WireError::InvalidLength(start))
+	}
+
+	fn check_end(&self, start: usize, end: usize) -> Result<(), WireError> {
+		if self.offset != end {
+			return Err(WireError::InvalidLength(start));
+		}
+		Ok(())
+	}
+}
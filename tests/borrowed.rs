@@ -0,0 +1,110 @@
+use serde::Deserialize;
+use std::{borrow::Cow, collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Borrowed<'a> {
+	name: &'a str,
+	blob: &'a [u8],
+	#[serde(borrow)]
+	label: Cow<'a, str>,
+	tags: HashMap<&'a str, u32>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+enum BorrowedEnum<'a> {
+	Name(&'a str),
+	Pair { key: &'a str, value: &'a [u8] },
+}
+
+fn message() -> Message {
+	Message::Dictionary(dict![
+		cstr!("name") => Message::String(cstr!("foo")),
+		cstr!("blob") => Message::Data(vec![0, 1, 2, 3]),
+		cstr!("label") => Message::String(cstr!("bar")),
+		cstr!("tags") => Message::Dictionary(dict![cstr!("baz") => Message::Uint64(42)])
+	])
+}
+
+#[test]
+fn borrows_strings_and_bytes() {
+	let message = message();
+	let decoded: Borrowed = xpc_serde::from_message_ref(&message).expect("failed to deserialize");
+	assert_eq!(decoded.name, "foo");
+	assert_eq!(decoded.blob, [0, 1, 2, 3]);
+	assert!(matches!(decoded.label, Cow::Borrowed("bar")));
+	assert_eq!(decoded.tags, dict!["baz" => 42]);
+
+	let map = match &message {
+		Message::Dictionary(map) => map,
+		_ => unreachable!(),
+	};
+	match &map[&cstr!("name")] {
+		Message::String(s) => assert_eq!(decoded.name.as_ptr(), s.as_ptr() as *const u8),
+		_ => unreachable!(),
+	}
+	match &map[&cstr!("blob")] {
+		Message::Data(data) => assert_eq!(decoded.blob.as_ptr(), data.as_ptr()),
+		_ => unreachable!(),
+	}
+}
+
+#[test]
+fn borrows_enum_contents() {
+	let message = Message::Dictionary(dict![cstr!("Name") => Message::String(cstr!("foo"))]);
+	let decoded: BorrowedEnum =
+		xpc_serde::from_message_ref(&message).expect("failed to deserialize");
+	assert_eq!(decoded, BorrowedEnum::Name("foo"));
+
+	let message = Message::Dictionary(dict![
+		cstr!("Pair") => Message::Dictionary(dict![
+			cstr!("key") => Message::String(cstr!("foo")),
+			cstr!("value") => Message::Data(vec![1, 2])
+		])
+	]);
+	let decoded: BorrowedEnum =
+		xpc_serde::from_message_ref(&message).expect("failed to deserialize");
+	assert_eq!(
+		decoded,
+		BorrowedEnum::Pair {
+			key: "foo",
+			value: &[1, 2]
+		}
+	);
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct Owned {
+	name: String,
+	blob: bytes::Bytes,
+	label: String,
+	tags: HashMap<String, u32>,
+}
+
+#[test]
+fn owned_types_from_reference() {
+	let message = message();
+	let decoded: Owned = xpc_serde::from_message_ref(&message).expect("failed to deserialize");
+	assert_eq!(decoded.name, "foo");
+	// The message is still usable afterwards.
+	let owned: Owned = xpc_serde::deserialize(message).expect("failed to deserialize");
+	assert_eq!(owned, decoded);
+}
@@ -0,0 +1,138 @@
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+#[cfg(feature = "digest")]
+use xpc_serde::canonical::digest;
+use xpc_serde::{canonical::canonical_bytes, wire, IntegerEncoding, SerializerConfig};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! same_canonical {
+	($($name:ident: $a:expr, $b:expr;)*) => {
+		$(
+			#[test]
+			fn $name() {
+				assert_eq!(canonical_bytes(&$a).unwrap(), canonical_bytes(&$b).unwrap());
+			}
+		)*
+	};
+}
+
+macro_rules! different_canonical {
+	($($name:ident: $a:expr, $b:expr;)*) => {
+		$(
+			#[test]
+			fn $name() {
+				assert_ne!(canonical_bytes(&$a).unwrap(), canonical_bytes(&$b).unwrap());
+			}
+		)*
+	};
+}
+
+same_canonical! {
+	non_negative_int64: Message::Int64(7), Message::Uint64(7);
+	zero: Message::Int64(0), Message::Uint64(0);
+	negative_zero: Message::Double(-0.0), Message::Double(0.0);
+	nan: Message::Double(f64::NAN), Message::Double(f64::from_bits(0xfff0_0000_dead_beef));
+	nested: Message::Array(vec![Message::Int64(1), Message::Double(-0.0)]),
+		Message::Array(vec![Message::Uint64(1), Message::Double(0.0)]);
+}
+
+different_canonical! {
+	negative_int64: Message::Int64(-1), Message::Uint64(u64::MAX);
+	integer_and_double: Message::Uint64(1), Message::Double(1.0);
+	string_and_data: Message::String(cstr!("a")), Message::Data(b"a".to_vec());
+	array_order: Message::Array(vec![Message::Bool(true), Message::Null]),
+		Message::Array(vec![Message::Null, Message::Bool(true)]);
+}
+
+/// Builds a dictionary, inserting its entries in the given order.
+fn dictionary<'a, I>(keys: I) -> Message
+where
+	I: Iterator<Item = &'a String>,
+{
+	let mut map = HashMap::new();
+	for key in keys {
+		map.insert(
+			CString::new(key.as_str()).unwrap(),
+			Message::String(CString::new(key.as_str()).unwrap()),
+		);
+	}
+	Message::Dictionary(map)
+}
+
+#[test]
+fn key_order() {
+	let keys = (0..64).map(|i| format!("key{}", i)).collect::<Vec<_>>();
+	assert_eq!(
+		canonical_bytes(&dictionary(keys.iter())).unwrap(),
+		canonical_bytes(&dictionary(keys.iter().rev())).unwrap()
+	);
+}
+
+#[test]
+fn integer_encodings() {
+	let value = (1u8, -2i32, u64::MAX, i64::MIN, "three");
+	let encoded = [
+		IntegerEncoding::PreserveSignedness,
+		IntegerEncoding::PreferInt64,
+		IntegerEncoding::PreferUint64,
+	]
+	.iter()
+	.map(|encoding| {
+		let message = SerializerConfig::new()
+			.integer_encoding(*encoding)
+			.serialize(&value)
+			.unwrap();
+		canonical_bytes(&message).unwrap()
+	})
+	.collect::<Vec<_>>();
+	assert!(encoded.iter().all(|bytes| *bytes == encoded[0]));
+}
+
+#[test]
+fn decodes_as_wire_format() {
+	let message = Message::Array(vec![
+		Message::Int64(5),
+		Message::Int64(-5),
+		Message::Double(-0.0),
+	]);
+	assert_eq!(
+		wire::decode(&canonical_bytes(&message).unwrap()).unwrap(),
+		Message::Array(vec![
+			Message::Uint64(5),
+			Message::Int64(-5),
+			Message::Double(0.0),
+		])
+	);
+}
+
+#[cfg(feature = "digest")]
+#[test]
+fn stable() {
+	// The digest of an empty dictionary must never change between versions.
+	assert_eq!(
+		digest(&Message::Dictionary(HashMap::new()))
+			.unwrap()
+			.iter()
+			.map(|b| format!("{:02x}", b))
+			.collect::<String>(),
+		"bb0f8b53c310961bb72f698fdc174ab5468bbe76133d1eb67e3d6725de7d02f5"
+	);
+}
+
+#[test]
+fn wire_encoding_unchanged() {
+	assert_ne!(
+		wire::encode(&Message::Int64(5)).unwrap(),
+		canonical_bytes(&Message::Int64(5)).unwrap()
+	);
+}
+
+#[test]
+fn unsupported() {
+	assert!(canonical_bytes(&Message::Uuid(vec![0; 3])).is_err());
+}
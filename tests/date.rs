@@ -0,0 +1,132 @@
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashMap,
+	convert::TryFrom,
+	ffi::CString,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use xpc_connection::Message;
+use xpc_serde::{
+	error::DeserializeError, Date, DeserializerConfig, IntegerEncoding, NumericCoercion,
+	SerializerConfig,
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Event {
+	name: String,
+	at: Date,
+}
+
+#[test]
+fn serializes_as_nanoseconds() {
+	let event = Event {
+		name: "boot".to_string(),
+		at: Date::from_nanoseconds(1_500_000_000),
+	};
+	let message = Message::Dictionary(dict![
+		cstr!("name") => Message::String(cstr!("boot")),
+		cstr!("at") => Message::Int64(1_500_000_000)
+	]);
+	assert_eq!(xpc_serde::serialize(&event).unwrap(), message);
+	assert_eq!(xpc_serde::deserialize::<Event>(message).unwrap(), event);
+}
+
+#[test]
+fn ignores_integer_encoding() {
+	let config = SerializerConfig::new().integer_encoding(IntegerEncoding::PreferUint64);
+	assert_eq!(
+		config.serialize(&Date::from_nanoseconds(1)).unwrap(),
+		Message::Int64(1)
+	);
+	assert_eq!(config.serialize(&1i64).unwrap(), Message::Uint64(1));
+}
+
+#[test]
+fn borrowed() {
+	let message = Message::Int64(-1);
+	assert_eq!(
+		xpc_serde::from_message_ref::<Date>(&message).unwrap(),
+		Date::from_nanoseconds(-1)
+	);
+}
+
+#[test]
+fn rejects_other_kinds() {
+	for message in [Message::Double(1.0), Message::String(cstr!("1970-01-01"))] {
+		assert!(matches!(
+			xpc_serde::deserialize::<Date>(message),
+			Err(DeserializeError::Unexpected("date", _))
+		));
+	}
+}
+
+#[test]
+fn uint64_when_lossless() {
+	assert_eq!(
+		xpc_serde::deserialize::<Date>(Message::Uint64(1)).unwrap(),
+		Date::from_nanoseconds(1)
+	);
+	assert!(matches!(
+		xpc_serde::deserialize::<Date>(Message::Uint64(u64::MAX)),
+		Err(DeserializeError::OutOfRange {
+			expected: "date",
+			..
+		})
+	));
+
+	let strict = DeserializerConfig::new().numeric_coercion(NumericCoercion::Strict);
+	assert!(matches!(
+		strict.deserialize::<Date>(Message::Uint64(1)),
+		Err(DeserializeError::Unexpected("date", "u64"))
+	));
+	assert_eq!(
+		strict.deserialize::<Date>(Message::Int64(1)).unwrap(),
+		Date::from_nanoseconds(1)
+	);
+}
+
+#[test]
+fn system_time() {
+	let time = UNIX_EPOCH + Duration::new(1_600_000_000, 123_456_789);
+	let date = Date::try_from(time).unwrap();
+	assert_eq!(date.nanoseconds(), 1_600_000_000_123_456_789);
+	assert_eq!(SystemTime::from(date), time);
+
+	let before = UNIX_EPOCH - Duration::from_nanos(5);
+	let date = Date::try_from(before).unwrap();
+	assert_eq!(date.nanoseconds(), -5);
+	assert_eq!(SystemTime::from(date), before);
+
+	assert_eq!(Date::try_from(UNIX_EPOCH).unwrap(), Date::UNIX_EPOCH);
+}
+
+#[test]
+fn out_of_range() {
+	let far = UNIX_EPOCH + Duration::from_secs(400 * 365 * 24 * 60 * 60);
+	assert!(Date::try_from(far).is_err());
+}
+
+#[test]
+fn plain_integer_elsewhere() {
+	let date = Date::from_nanoseconds(42);
+	assert_eq!(serde_json::to_string(&date).unwrap(), "42");
+	assert_eq!(serde_json::from_str::<Date>("42").unwrap(), date);
+}
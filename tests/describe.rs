@@ -0,0 +1,163 @@
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+use xpc_serde::DescriptionConfig;
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+macro_rules! describes {
+	($name:ident, $message:expr, $expected:expr) => {
+		describes!($name, DescriptionConfig::new(), $message, $expected);
+	};
+	($name:ident, $config:expr, $message:expr, $expected:expr) => {
+		#[test]
+		fn $name() {
+			assert_eq!($config.describe(&$message).to_string(), $expected);
+		}
+	};
+}
+
+fn nested() -> Message {
+	Message::Dictionary(dict![
+		cstr!("name") => Message::String(cstr!("main")),
+		cstr!("sources") => Message::Array(vec![
+			Message::Dictionary(dict![cstr!("port") => Message::Uint64(80)]),
+			Message::Array(vec![])
+		]),
+		cstr!("enabled") => Message::Bool(true)
+	])
+}
+
+describes!(null, Message::Null, "<null>");
+describes!(bool, Message::Bool(false), "<bool: false>");
+describes!(int64, Message::Int64(-42), "<int64: -42>");
+describes!(uint64, Message::Uint64(42), "<uint64: 42>");
+describes!(double, Message::Double(1.0), "<double: 1.0>");
+describes!(
+	string,
+	Message::String(cstr!("say \"hi\"")),
+	r#"<string> { length = 8, contents = "say \"hi\"" }"#
+);
+describes!(
+	data,
+	Message::Data(vec![0x00, 0xab, 0xff]),
+	"<data> { length = 3 bytes, contents = 0x00abff }"
+);
+describes!(
+	data_truncated,
+	DescriptionConfig::new().max_data_length(Some(2)),
+	Message::Data(vec![0x00, 0xab, 0xff]),
+	"<data> { length = 3 bytes, contents = 0x00ab... }"
+);
+describes!(
+	data_untruncated,
+	DescriptionConfig::new().max_data_length(None),
+	Message::Data(vec![0x11; 100]),
+	format!(
+		"<data> {{ length = 100 bytes, contents = 0x{} }}",
+		"11".repeat(100)
+	)
+);
+describes!(
+	uuid,
+	Message::Uuid((0..16).collect()),
+	"<uuid: 00010203-0405-0607-0809-0a0b0c0d0e0f>"
+);
+describes!(empty_array, Message::Array(vec![]), "<array> { count = 0 }");
+describes!(
+	empty_dictionary,
+	Message::Dictionary(HashMap::new()),
+	"<dictionary> { count = 0 }"
+);
+describes!(
+	indented,
+	nested(),
+	"<dictionary> { count = 3, contents =
+	\"enabled\" => <bool: true>
+	\"name\" => <string> { length = 4, contents = \"main\" }
+	\"sources\" => <array> { count = 2, contents =
+		0: <dictionary> { count = 1, contents =
+			\"port\" => <uint64: 80>
+		}
+		1: <array> { count = 0 }
+	}
+}"
+);
+describes!(
+	custom_indent,
+	DescriptionConfig::new().indent(Some("  ")),
+	Message::Array(vec![Message::Array(vec![Message::Null])]),
+	"<array> { count = 1, contents =
+  0: <array> { count = 1, contents =
+    0: <null>
+  }
+}"
+);
+describes!(
+	single_line,
+	DescriptionConfig::new().indent(None),
+	nested(),
+	"<dictionary> { count = 3, contents = \"enabled\" => <bool: true>, \
+	 \"name\" => <string> { length = 4, contents = \"main\" }, \
+	 \"sources\" => <array> { count = 2, contents = \
+	 0: <dictionary> { count = 1, contents = \"port\" => <uint64: 80> }, \
+	 1: <array> { count = 0 } } }"
+);
+describes!(
+	max_depth,
+	DescriptionConfig::new().indent(None).max_depth(Some(1)),
+	nested(),
+	"<dictionary> { count = 3, contents = \"enabled\" => <bool: true>, \
+	 \"name\" => <string> { length = 4, contents = \"main\" }, \
+	 \"sources\" => <array> { count = 2, contents = ... } }"
+);
+describes!(
+	max_depth_zero,
+	DescriptionConfig::new().max_depth(Some(0)),
+	nested(),
+	"<dictionary> { count = 3, contents = ... }"
+);
+
+#[test]
+fn unsorted_keys() {
+	let message = Message::Dictionary(dict![
+		cstr!("a") => Message::Null,
+		cstr!("b") => Message::Null,
+		cstr!("c") => Message::Null
+	]);
+	let description = DescriptionConfig::new()
+		.sort_keys(false)
+		.describe(&message)
+		.to_string();
+	for key in &["\"a\" => <null>", "\"b\" => <null>", "\"c\" => <null>"] {
+		assert!(
+			description.contains(key),
+			"{} missing from {}",
+			key,
+			description
+		);
+	}
+}
+
+#[test]
+fn describe_uses_defaults() {
+	assert_eq!(
+		xpc_serde::describe(&nested()).to_string(),
+		DescriptionConfig::new().describe(&nested()).to_string()
+	);
+}
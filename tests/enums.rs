@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+use xpc_serde::{
+	error::DeserializeError, DeserializerConfig, EnumRepresentation, IntegerEncoding,
+	NumericCoercion, SerializerConfig,
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+macro_rules! enum_round_trip {
+	($name:ident, $representation:expr, $value:expr, $expected:expr) => {
+		#[test]
+		fn $name() {
+			let initial: TestEnum = $value;
+			let encoded = SerializerConfig::new()
+				.enum_representation($representation)
+				.serialize(&initial)
+				.expect("failed to serialize");
+			assert_eq!(encoded, $expected);
+			let decoded = DeserializerConfig::new()
+				.enum_representation($representation)
+				.deserialize::<TestEnum>(encoded)
+				.expect("failed to deserialize");
+			assert_eq!(decoded, initial);
+		}
+	};
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum TestEnum {
+	Simple,
+	Single(u32),
+	Tuple(u32, u32),
+	Struct { a: u32, b: String },
+}
+
+enum_round_trip!(
+	index_simple,
+	EnumRepresentation::Index,
+	TestEnum::Simple,
+	Message::Uint64(0)
+);
+enum_round_trip!(
+	index_single,
+	EnumRepresentation::Index,
+	TestEnum::Single(42),
+	Message::Dictionary(dict![
+		cstr!("type") => Message::Uint64(1),
+		cstr!("value") => Message::Uint64(42)
+	])
+);
+enum_round_trip!(
+	index_tuple,
+	EnumRepresentation::Index,
+	TestEnum::Tuple(1, 2),
+	Message::Dictionary(dict![
+		cstr!("type") => Message::Uint64(2),
+		cstr!("value") => Message::Array(vec![Message::Uint64(1), Message::Uint64(2)])
+	])
+);
+enum_round_trip!(
+	index_struct,
+	EnumRepresentation::Index,
+	TestEnum::Struct {
+		a: 1,
+		b: "foo".to_string()
+	},
+	Message::Dictionary(dict![
+		cstr!("type") => Message::Uint64(3),
+		cstr!("value") => Message::Dictionary(dict![
+			cstr!("a") => Message::Uint64(1),
+			cstr!("b") => Message::String(cstr!("foo"))
+		])
+	])
+);
+enum_round_trip!(
+	type_value_simple,
+	EnumRepresentation::TypeValue,
+	TestEnum::Simple,
+	Message::Dictionary(dict![cstr!("type") => Message::String(cstr!("Simple"))])
+);
+enum_round_trip!(
+	type_value_single,
+	EnumRepresentation::TypeValue,
+	TestEnum::Single(42),
+	Message::Dictionary(dict![
+		cstr!("type") => Message::String(cstr!("Single")),
+		cstr!("value") => Message::Uint64(42)
+	])
+);
+enum_round_trip!(
+	type_value_tuple,
+	EnumRepresentation::TypeValue,
+	TestEnum::Tuple(1, 2),
+	Message::Dictionary(dict![
+		cstr!("type") => Message::String(cstr!("Tuple")),
+		cstr!("value") => Message::Array(vec![Message::Uint64(1), Message::Uint64(2)])
+	])
+);
+enum_round_trip!(
+	type_value_struct,
+	EnumRepresentation::TypeValue,
+	TestEnum::Struct {
+		a: 1,
+		b: "foo".to_string()
+	},
+	Message::Dictionary(dict![
+		cstr!("type") => Message::String(cstr!("Struct")),
+		cstr!("value") => Message::Dictionary(dict![
+			cstr!("a") => Message::Uint64(1),
+			cstr!("b") => Message::String(cstr!("foo"))
+		])
+	])
+);
+
+#[test]
+fn index_accepts_int64() {
+	let decoded = DeserializerConfig::new()
+		.enum_representation(EnumRepresentation::Index)
+		.deserialize::<TestEnum>(Message::Dictionary(dict![
+			cstr!("type") => Message::Int64(1),
+			cstr!("value") => Message::Int64(42)
+		]))
+		.expect("failed to deserialize");
+	assert_eq!(decoded, TestEnum::Single(42));
+}
+
+#[test]
+fn index_ignores_integer_settings() {
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	enum Signed {
+		A,
+		B(i64),
+	}
+
+	let ser = SerializerConfig::new()
+		.enum_representation(EnumRepresentation::Index)
+		.integer_encoding(IntegerEncoding::PreferInt64);
+	let de = DeserializerConfig::new()
+		.enum_representation(EnumRepresentation::Index)
+		.numeric_coercion(NumericCoercion::Strict);
+	for (value, expected) in [
+		(Signed::A, Message::Uint64(0)),
+		(
+			Signed::B(3),
+			Message::Dictionary(dict![
+				cstr!("type") => Message::Uint64(1),
+				cstr!("value") => Message::Int64(3)
+			]),
+		),
+	] {
+		let encoded = ser.serialize(&value).expect("failed to serialize");
+		assert_eq!(encoded, expected);
+		assert_eq!(
+			de.deserialize::<Signed>(encoded)
+				.expect("failed to deserialize"),
+			value
+		);
+	}
+
+	// Indices sent as int64 are accepted under strict coercion, but still
+	// range-checked.
+	assert_eq!(
+		de.deserialize::<Signed>(Message::Int64(0))
+			.expect("failed to deserialize"),
+		Signed::A
+	);
+	assert!(matches!(
+		de.deserialize::<Signed>(Message::Int64(-1)),
+		Err(DeserializeError::OutOfRange {
+			expected: "u64",
+			value: -1
+		})
+	));
+}
+
+#[test]
+fn type_value_missing_type() {
+	let result = DeserializerConfig::new()
+		.enum_representation(EnumRepresentation::TypeValue)
+		.deserialize::<TestEnum>(Message::Dictionary(
+			dict![cstr!("value") => Message::Uint64(42)],
+		));
+	match result {
+		Err(DeserializeError::MissingField("type")) => {}
+		other => panic!("expected MissingField, got {:?}", other),
+	}
+}
+
+#[test]
+fn type_value_missing_value() {
+	let result = DeserializerConfig::new()
+		.enum_representation(EnumRepresentation::TypeValue)
+		.deserialize::<TestEnum>(Message::Dictionary(
+			dict![cstr!("type") => Message::String(cstr!("Single"))],
+		));
+	match result {
+		Err(DeserializeError::MissingField("value")) => {}
+		other => panic!("expected MissingField, got {:?}", other),
+	}
+}
+
+#[test]
+fn ambiguous_enum() {
+	let result = xpc_serde::deserialize::<TestEnum>(Message::Dictionary(dict![
+		cstr!("Single") => Message::Uint64(1),
+		cstr!("Simple") => Message::Null
+	]));
+	match result {
+		Err(DeserializeError::AmbiguousEnum(keys)) => assert_eq!(keys, ["Simple", "Single"]),
+		other => panic!("expected AmbiguousEnum, got {:?}", other),
+	}
+}
+
+#[test]
+fn empty_enum() {
+	let result = xpc_serde::deserialize::<TestEnum>(Message::Dictionary(HashMap::new()));
+	match result {
+		Err(DeserializeError::AmbiguousEnum(keys)) => assert!(keys.is_empty()),
+		other => panic!("expected AmbiguousEnum, got {:?}", other),
+	}
+}
+
+#[test]
+fn unknown_variant() {
+	let result = xpc_serde::deserialize::<TestEnum>(Message::Dictionary(
+		dict![cstr!("Nope") => Message::Uint64(1)],
+	));
+	match result {
+		Err(DeserializeError::UnknownVariant { variant, expected }) => {
+			assert_eq!(variant, "Nope");
+			assert_eq!(expected, ["Simple", "Single", "Tuple", "Struct"]);
+		}
+		other => panic!("expected UnknownVariant, got {:?}", other),
+	}
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+enum OtherEnum {
+	Known,
+	#[serde(other)]
+	Other,
+}
+
+#[test]
+fn other_variant() {
+	let decoded = xpc_serde::deserialize::<OtherEnum>(Message::String(cstr!("Nope")))
+		.expect("failed to deserialize");
+	assert_eq!(decoded, OtherEnum::Other);
+	let decoded = xpc_serde::deserialize::<OtherEnum>(Message::String(cstr!("Known")))
+		.expect("failed to deserialize");
+	assert_eq!(decoded, OtherEnum::Known);
+}
+
+#[test]
+fn other_variant_type_value() {
+	let decoded = DeserializerConfig::new()
+		.enum_representation(EnumRepresentation::TypeValue)
+		.deserialize::<OtherEnum>(Message::Dictionary(
+			dict![cstr!("type") => Message::String(cstr!("Nope"))],
+		))
+		.expect("failed to deserialize");
+	assert_eq!(decoded, OtherEnum::Other);
+}
@@ -0,0 +1,207 @@
+#![cfg(feature = "json")]
+
+use serde_json::json;
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::{Message, MessageError};
+use xpc_serde::{
+	error::JsonError,
+	json::{from_json, to_json},
+	Uuid,
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+/// Converts the message to JSON, checks the JSON, then prints and parses it
+/// again to make sure the conversion back is exact.
+macro_rules! json_round_trip {
+	($name:ident, $message:expr, $json:expr) => {
+		#[test]
+		fn $name() {
+			let message: Message = $message;
+			let value = to_json(&message).expect("failed to convert to JSON");
+			assert_eq!(value, $json);
+			let text = serde_json::to_string(&value).expect("failed to print JSON");
+			let parsed = serde_json::from_str(&text).expect("failed to parse JSON");
+			assert_eq!(
+				from_json(&parsed).expect("failed to convert from JSON"),
+				message
+			);
+		}
+	};
+}
+
+json_round_trip!(null, Message::Null, json!(null));
+json_round_trip!(bool, Message::Bool(true), json!(true));
+json_round_trip!(int64, Message::Int64(-1), json!(-1));
+json_round_trip!(int64_positive, Message::Int64(1), json!(1));
+json_round_trip!(uint64, Message::Uint64(1), json!({ "$uint64": 1 }));
+json_round_trip!(
+	uint64_max,
+	Message::Uint64(u64::MAX),
+	json!({ "$uint64": u64::MAX })
+);
+json_round_trip!(double, Message::Double(1.0), json!(1.0));
+json_round_trip!(double_negative_zero, Message::Double(-0.0), json!(-0.0));
+json_round_trip!(
+	double_infinity,
+	Message::Double(f64::INFINITY),
+	json!({ "$double": "Infinity" })
+);
+json_round_trip!(
+	double_negative_infinity,
+	Message::Double(f64::NEG_INFINITY),
+	json!({ "$double": "-Infinity" })
+);
+json_round_trip!(string, Message::String(cstr!("text")), json!("text"));
+json_round_trip!(
+	data,
+	Message::Data(vec![1, 2, 3]),
+	json!({ "$data": "AQID" })
+);
+json_round_trip!(data_empty, Message::Data(vec![]), json!({ "$data": "" }));
+json_round_trip!(
+	uuid,
+	Message::Uuid((0..16).collect()),
+	json!({ "$uuid": "00010203-0405-0607-0809-0a0b0c0d0e0f" })
+);
+json_round_trip!(
+	array,
+	Message::Array(vec![Message::Int64(1), Message::Uint64(1), Message::Null]),
+	json!([1, { "$uint64": 1 }, null])
+);
+json_round_trip!(
+	dictionary,
+	Message::Dictionary(dict![
+		cstr!("a") => Message::Int64(1),
+		cstr!("b") => Message::Data(vec![0xff])
+	]),
+	json!({ "a": 1, "b": { "$data": "/w==" } })
+);
+json_round_trip!(
+	dictionary_looking_like_tag,
+	Message::Dictionary(dict![cstr!("$data") => Message::String(cstr!("AQID"))]),
+	json!({ "$dictionary": { "$data": "AQID" } })
+);
+json_round_trip!(
+	dictionary_with_dollar_keys,
+	Message::Dictionary(dict![
+		cstr!("$a") => Message::Null,
+		cstr!("$b") => Message::Null
+	]),
+	json!({ "$a": null, "$b": null })
+);
+json_round_trip!(
+	nested,
+	Message::Dictionary(dict![
+		cstr!("sources") => Message::Array(vec![Message::Dictionary(dict![
+			cstr!("port") => Message::Uint64(80),
+			cstr!("weight") => Message::Double(0.5)
+		])])
+	]),
+	json!({ "sources": [{ "port": { "$uint64": 80 }, "weight": 0.5 }] })
+);
+
+#[test]
+fn nan() {
+	let value = to_json(&Message::Double(f64::NAN)).expect("failed to convert to JSON");
+	assert_eq!(value, json!({ "$double": "NaN" }));
+	match from_json(&value).expect("failed to convert from JSON") {
+		Message::Double(v) => assert!(v.is_nan()),
+		message => panic!("expected a double, got {:?}", message),
+	}
+}
+
+#[test]
+fn large_plain_integer() {
+	assert_eq!(
+		from_json(&json!(u64::MAX)).expect("failed to convert from JSON"),
+		Message::Uint64(u64::MAX)
+	);
+}
+
+#[test]
+fn unknown_tag() {
+	assert!(matches!(
+		from_json(&json!({ "$date": 0 })),
+		Err(JsonError::InvalidTag(tag)) if tag == "$date"
+	));
+}
+
+#[test]
+fn invalid_tag_contents() {
+	assert!(from_json(&json!({ "$uint64": -1 })).is_err());
+	assert!(from_json(&json!({ "$double": "1.0" })).is_err());
+	assert!(matches!(
+		from_json(&json!({ "$data": "not base64!" })),
+		Err(JsonError::InvalidBase64(_))
+	));
+}
+
+#[test]
+fn nul_in_string() {
+	assert!(matches!(
+		from_json(&json!({ "a\u{0}b": null })),
+		Err(JsonError::NulString(_))
+	));
+}
+
+#[test]
+fn invalid_utf8() {
+	let string = CString::new(vec![0xf0, 0x28, 0x8c, 0xbc]).expect("failed to create CString");
+	assert!(matches!(
+		to_json(&Message::String(string)),
+		Err(JsonError::InvalidStr(_))
+	));
+}
+
+#[test]
+fn unsupported() {
+	assert!(matches!(
+		to_json(&Message::Array(vec![Message::Error(
+			MessageError::ConnectionInvalid
+		)])),
+		Err(JsonError::Unsupported("error"))
+	));
+}
+
+#[test]
+fn invalid_uuid() {
+	assert!(matches!(
+		to_json(&Message::Uuid(vec![0; 3])),
+		Err(JsonError::InvalidUuid(3))
+	));
+	assert!(matches!(
+		from_json(&json!({ "$uuid": "0001" })),
+		Err(JsonError::InvalidTag(tag)) if tag == "$uuid"
+	));
+}
+
+#[test]
+fn uuid_value() {
+	let uuid: Uuid = "00010203-0405-0607-0809-0a0b0c0d0e0f".parse().unwrap();
+	let json = to_json(&xpc_serde::serialize(&uuid).unwrap()).unwrap();
+	assert_eq!(
+		json,
+		json!({ "$uuid": "00010203-0405-0607-0809-0a0b0c0d0e0f" })
+	);
+	assert_eq!(
+		xpc_serde::deserialize::<Uuid>(from_json(&json).unwrap()).unwrap(),
+		uuid
+	);
+}
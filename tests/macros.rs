@@ -0,0 +1,126 @@
+use serde::Serialize;
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+use xpc_serde::xpc;
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+macro_rules! builds {
+	($name:ident, $built:expr, $expected:expr) => {
+		#[test]
+		fn $name() {
+			assert_eq!($built, $expected);
+		}
+	};
+}
+
+#[derive(Serialize)]
+struct Source {
+	port: u16,
+}
+
+builds!(null, xpc!(null), Message::Null);
+builds!(bool, xpc!(true), Message::Bool(true));
+builds!(signed, xpc!(-1), Message::Int64(-1));
+builds!(unsigned, xpc!(42u64), Message::Uint64(42));
+builds!(double, xpc!(0.5), Message::Double(0.5));
+builds!(string, xpc!("bar"), Message::String(cstr!("bar")));
+builds!(empty_array, xpc!([]), Message::Array(vec![]));
+builds!(
+	empty_dictionary,
+	xpc!({}),
+	Message::Dictionary(HashMap::new())
+);
+builds!(
+	array,
+	xpc!([1, null, "a", [], {}, [2u8], { "b": false },]),
+	Message::Array(vec![
+		Message::Int64(1),
+		Message::Null,
+		Message::String(cstr!("a")),
+		Message::Array(vec![]),
+		Message::Dictionary(HashMap::new()),
+		Message::Array(vec![Message::Uint64(2)]),
+		Message::Dictionary(dict![cstr!("b") => Message::Bool(false)])
+	])
+);
+builds!(
+	dictionary,
+	xpc!({ "foo": "bar", "n": 42u64, "xs": [1, 2] }),
+	Message::Dictionary(dict![
+		cstr!("foo") => Message::String(cstr!("bar")),
+		cstr!("n") => Message::Uint64(42),
+		cstr!("xs") => Message::Array(vec![Message::Int64(1), Message::Int64(2)])
+	])
+);
+builds!(
+	nested,
+	xpc!({
+		"settings": {
+			"sources": [{ "port": 80u16 }, { "port": null }],
+			"enabled": true,
+		},
+	}),
+	Message::Dictionary(dict![
+		cstr!("settings") => Message::Dictionary(dict![
+			cstr!("sources") => Message::Array(vec![
+				Message::Dictionary(dict![cstr!("port") => Message::Uint64(80)]),
+				Message::Dictionary(dict![cstr!("port") => Message::Null])
+			]),
+			cstr!("enabled") => Message::Bool(true)
+		])
+	])
+);
+builds!(
+	interpolated,
+	{
+		let source = Source { port: 80 };
+		let ports = vec![1u16, 2];
+		xpc!({ "source": source, "ports": ports, "sum": ports[0] + ports[1] })
+	},
+	Message::Dictionary(dict![
+		cstr!("source") => Message::Dictionary(dict![cstr!("port") => Message::Uint64(80)]),
+		cstr!("ports") => Message::Array(vec![Message::Uint64(1), Message::Uint64(2)]),
+		cstr!("sum") => Message::Uint64(3)
+	])
+);
+builds!(
+	expression_keys,
+	{
+		let key = "dynamic";
+		xpc!({ (key): 1u8, (format!("{}{}", key, 2)): 2u8 })
+	},
+	Message::Dictionary(dict![
+		cstr!("dynamic") => Message::Uint64(1),
+		cstr!("dynamic2") => Message::Uint64(2)
+	])
+);
+
+#[test]
+#[should_panic(expected = "xpc! dictionary keys must not contain NUL bytes")]
+fn nul_in_expression_key() {
+	let key = "a\0b";
+	let _ = xpc!({ (key): 1 });
+}
+
+#[test]
+#[should_panic(expected = "failed to serialize xpc! value")]
+fn nul_in_value() {
+	let _ = xpc!({ "a": "a\0b" });
+}
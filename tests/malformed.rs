@@ -0,0 +1,193 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect("failed to create CString")
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+/// Feeds every adversarial message into the deserializer for the given types.
+/// Errors are fine, panics are not.
+macro_rules! no_panic {
+	($name:ident, $($type:ty),+ $(,)?) => {
+		#[test]
+		fn $name() {
+			$(
+				for message in adversarial() {
+					let _ = xpc_serde::from_message_ref::<$type>(&message);
+					let _ = xpc_serde::deserialize::<$type>(message);
+				}
+			)+
+		}
+	};
+}
+
+fn invalid_utf8() -> CString {
+	CString::new(vec![0xf0, 0x28, 0x8c, 0xbc]).expect("failed to create CString")
+}
+
+fn scalars() -> Vec<Message> {
+	vec![
+		Message::Bool(true),
+		Message::Double(f64::NAN),
+		Message::Double(f64::INFINITY),
+		Message::Double(-0.0),
+		Message::Int64(i64::MIN),
+		Message::Int64(-1),
+		Message::Int64(300),
+		Message::Uint64(u64::MAX),
+		Message::Uint64(0x1_0000_0061),
+		Message::Uint64(0xd800),
+		Message::String(cstr!("")),
+		Message::String(cstr!("a")),
+		Message::String(cstr!("Simple")),
+		Message::String(cstr!("not a variant")),
+		Message::String(invalid_utf8()),
+		Message::Data(vec![]),
+		Message::Data(vec![0xff; 64]),
+		Message::Null,
+	]
+}
+
+fn adversarial() -> Vec<Message> {
+	let mut messages = scalars();
+	messages.push(Message::Array(vec![]));
+	messages.push(Message::Array(scalars()));
+	messages.push(Message::Array(vec![Message::Array(scalars())]));
+	messages.push(Message::Dictionary(HashMap::new()));
+	messages.push(Message::Dictionary(dict![
+		cstr!("Simple") => Message::Null,
+		cstr!("Single") => Message::Uint64(1)
+	]));
+	messages.push(Message::Dictionary(dict![invalid_utf8() => Message::Null]));
+	messages.push(Message::Dictionary(dict![cstr!("-1") => Message::Null]));
+	messages.push(Message::Dictionary(dict![
+		cstr!("a") => Message::String(invalid_utf8()),
+		cstr!("b") => Message::Array(scalars()),
+		cstr!("c") => Message::Dictionary(dict![cstr!("a") => Message::Data(vec![1])])
+	]));
+	for variant in &["Simple", "Single", "Tuple", "Struct", "Unknown"] {
+		for message in scalars() {
+			messages.push(Message::Dictionary(dict![cstr!(*variant) => message]));
+		}
+		messages.push(Message::Dictionary(dict![
+			cstr!(*variant) => Message::Array(scalars())
+		]));
+		messages.push(Message::Dictionary(dict![
+			cstr!(*variant) => Message::Dictionary(dict![
+				cstr!("a") => Message::String(invalid_utf8()),
+				cstr!("b") => Message::Int64(-1)
+			])
+		]));
+	}
+	messages
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UnitStruct;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NewtypeStruct(u32);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TupleStruct(u32, String);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TestStruct {
+	a: u32,
+	b: String,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum TestEnum {
+	Simple,
+	Single(u32),
+	Tuple(u32, u32),
+	Struct { a: u32, b: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum UntaggedEnum {
+	Integer(u64),
+	Text(String),
+	Struct { a: u32, b: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+enum InternallyTaggedEnum {
+	Simple,
+	Struct { a: u32, b: String },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "t", content = "c")]
+enum AdjacentlyTaggedEnum {
+	Simple,
+	Single(u32),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Flattened {
+	a: u32,
+	#[serde(flatten)]
+	rest: HashMap<String, u64>,
+}
+
+no_panic!(no_panic_bool, bool);
+no_panic!(no_panic_signed, i8, i16, i32, i64);
+no_panic!(no_panic_unsigned, u8, u16, u32, u64);
+no_panic!(no_panic_float, f32, f64);
+no_panic!(no_panic_char, char);
+no_panic!(no_panic_string, String);
+no_panic!(no_panic_bytes, Bytes);
+no_panic!(no_panic_option, Option<String>, Option<Option<u64>>);
+no_panic!(no_panic_unit, (), UnitStruct);
+no_panic!(no_panic_newtype_struct, NewtypeStruct);
+no_panic!(no_panic_seq, Vec<u64>, Vec<String>, Vec<Vec<u8>>);
+no_panic!(no_panic_tuple, (String, u64), (u8,), TupleStruct);
+no_panic!(
+	no_panic_map,
+	HashMap<String, u64>,
+	HashMap<u32, String>,
+	HashMap<char, bool>,
+	HashMap<TestEnum, u64>,
+);
+no_panic!(no_panic_struct, TestStruct, Flattened);
+no_panic!(no_panic_enum, TestEnum, Vec<TestEnum>);
+no_panic!(
+	no_panic_tagged_enum,
+	UntaggedEnum,
+	InternallyTaggedEnum,
+	AdjacentlyTaggedEnum,
+);
+
+#[test]
+fn invalid_utf8_in_untagged_enum() {
+	let result = xpc_serde::deserialize::<UntaggedEnum>(Message::String(invalid_utf8()));
+	assert!(result.is_err());
+}
+
+#[test]
+fn invalid_utf8_in_map_key() {
+	let result = xpc_serde::deserialize::<HashMap<String, ()>>(Message::Dictionary(
+		dict![invalid_utf8() => Message::Null],
+	));
+	assert!(result.is_err());
+}
@@ -0,0 +1,313 @@
+use std::ffi::CString;
+use xpc_connection::Message;
+use xpc_serde::{
+	error::DeserializeError, DeserializerConfig, IntegerEncoding, NumericCoercion,
+	SerializerConfig, WideIntegerEncoding,
+};
+
+macro_rules! out_of_range {
+	($name:ident, $type:ty, $message:expr, $value:expr) => {
+		#[test]
+		fn $name() {
+			match xpc_serde::deserialize::<$type>($message) {
+				Err(DeserializeError::OutOfRange { expected, value }) => {
+					assert_eq!(expected, stringify!($type));
+					assert_eq!(value, $value);
+				}
+				other => panic!("expected OutOfRange, got {:?}", other),
+			}
+		}
+	};
+}
+
+macro_rules! in_range {
+	($name:ident, $type:ty, $message:expr, $expected:expr) => {
+		#[test]
+		fn $name() {
+			let decoded = xpc_serde::deserialize::<$type>($message).expect("failed to deserialize");
+			assert_eq!(decoded, $expected);
+		}
+	};
+}
+
+out_of_range!(out_of_range_i8, i8, Message::Int64(300), 300);
+out_of_range!(out_of_range_i8_negative, i8, Message::Int64(-129), -129);
+out_of_range!(out_of_range_i16, i16, Message::Int64(40000), 40000);
+out_of_range!(
+	out_of_range_i32,
+	i32,
+	Message::Int64(i64::MAX),
+	i128::from(i64::MAX)
+);
+out_of_range!(
+	out_of_range_i32_unsigned,
+	i32,
+	Message::Uint64(u64::MAX),
+	i128::from(u64::MAX)
+);
+out_of_range!(out_of_range_u8, u8, Message::Uint64(300), 300);
+out_of_range!(out_of_range_u16, u16, Message::Uint64(70000), 70000);
+out_of_range!(
+	out_of_range_u32,
+	u32,
+	Message::Uint64(u64::MAX),
+	i128::from(u64::MAX)
+);
+out_of_range!(out_of_range_u32_negative, u32, Message::Int64(-1), -1);
+
+in_range!(in_range_i8, i8, Message::Int64(-128), i8::MIN);
+in_range!(in_range_i16, i16, Message::Int64(-32768), i16::MIN);
+in_range!(in_range_i32, i32, Message::Uint64(42), 42);
+in_range!(in_range_u8, u8, Message::Uint64(255), u8::MAX);
+in_range!(in_range_u16, u16, Message::Uint64(65535), u16::MAX);
+in_range!(in_range_u32, u32, Message::Int64(42), 42);
+
+#[test]
+fn out_of_range_char() {
+	assert!(xpc_serde::deserialize::<char>(Message::Uint64(0x1_0000_0061)).is_err());
+}
+
+in_range!(lossless_i64_from_uint64, i64, Message::Uint64(42), 42);
+in_range!(lossless_u64_from_int64, u64, Message::Int64(42), 42);
+in_range!(lossless_i8_from_uint64, i8, Message::Uint64(42), 42);
+in_range!(lossless_u8_from_int64, u8, Message::Int64(42), 42);
+in_range!(lossless_f64_from_int64, f64, Message::Int64(-42), -42.0);
+in_range!(
+	lossless_f64_from_uint64,
+	f64,
+	Message::Uint64(1 << 53),
+	9007199254740992.0
+);
+in_range!(
+	lossless_f32_from_uint64,
+	f32,
+	Message::Uint64(1 << 24),
+	16777216.0
+);
+out_of_range!(lossless_u64_from_negative, u64, Message::Int64(-1), -1);
+out_of_range!(
+	lossless_i64_from_large,
+	i64,
+	Message::Uint64(u64::MAX),
+	i128::from(u64::MAX)
+);
+out_of_range!(
+	lossless_f64_inexact,
+	f64,
+	Message::Uint64((1 << 53) + 1),
+	(1 << 53) + 1
+);
+out_of_range!(
+	lossless_f32_inexact,
+	f32,
+	Message::Uint64((1 << 24) + 1),
+	(1 << 24) + 1
+);
+
+macro_rules! strict_rejects {
+	($name:ident, $type:ty, $message:expr, $expected:expr, $got:expr) => {
+		#[test]
+		fn $name() {
+			let config = DeserializerConfig::new().numeric_coercion(NumericCoercion::Strict);
+			match config.deserialize::<$type>($message) {
+				Err(DeserializeError::Unexpected(expected, got)) => {
+					assert_eq!(expected, $expected);
+					assert_eq!(got, $got);
+				}
+				other => panic!("expected Unexpected, got {:?}", other),
+			}
+		}
+	};
+}
+
+strict_rejects!(strict_i8_from_uint64, i8, Message::Uint64(42), "i64", "u64");
+strict_rejects!(
+	strict_i32_from_uint64,
+	i32,
+	Message::Uint64(42),
+	"i64",
+	"u64"
+);
+strict_rejects!(
+	strict_i64_from_uint64,
+	i64,
+	Message::Uint64(42),
+	"i64",
+	"u64"
+);
+strict_rejects!(strict_u32_from_int64, u32, Message::Int64(42), "u64", "i64");
+strict_rejects!(strict_u64_from_int64, u64, Message::Int64(42), "u64", "i64");
+strict_rejects!(strict_f32_from_int64, f32, Message::Int64(42), "f64", "i64");
+strict_rejects!(
+	strict_f64_from_uint64,
+	f64,
+	Message::Uint64(42),
+	"f64",
+	"u64"
+);
+
+#[test]
+fn strict_accepts_native() {
+	let config = DeserializerConfig::new().numeric_coercion(NumericCoercion::Strict);
+	assert_eq!(config.deserialize::<i8>(Message::Int64(-1)).ok(), Some(-1));
+	assert_eq!(config.deserialize::<u16>(Message::Uint64(1)).ok(), Some(1));
+	assert_eq!(
+		config.deserialize::<f64>(Message::Double(0.5)).ok(),
+		Some(0.5)
+	);
+}
+
+macro_rules! encodes {
+	($name:ident, $encoding:expr, $value:expr, $expected:expr) => {
+		#[test]
+		fn $name() {
+			let config = SerializerConfig::new().integer_encoding($encoding);
+			let encoded = config.serialize(&$value).expect("failed to serialize");
+			assert_eq!(encoded, $expected);
+		}
+	};
+}
+
+encodes!(
+	preserve_signed,
+	IntegerEncoding::PreserveSignedness,
+	42i8,
+	Message::Int64(42)
+);
+encodes!(
+	preserve_unsigned,
+	IntegerEncoding::PreserveSignedness,
+	42u8,
+	Message::Uint64(42)
+);
+encodes!(
+	prefer_int64_unsigned,
+	IntegerEncoding::PreferInt64,
+	42u32,
+	Message::Int64(42)
+);
+encodes!(
+	prefer_int64_signed,
+	IntegerEncoding::PreferInt64,
+	-42i32,
+	Message::Int64(-42)
+);
+encodes!(
+	prefer_int64_too_large,
+	IntegerEncoding::PreferInt64,
+	u64::MAX,
+	Message::Uint64(u64::MAX)
+);
+encodes!(
+	prefer_uint64_signed,
+	IntegerEncoding::PreferUint64,
+	42i16,
+	Message::Uint64(42)
+);
+encodes!(
+	prefer_uint64_negative,
+	IntegerEncoding::PreferUint64,
+	-1i64,
+	Message::Int64(-1)
+);
+encodes!(
+	prefer_int64_nested,
+	IntegerEncoding::PreferInt64,
+	vec![1u64, 2u64],
+	Message::Array(vec![Message::Int64(1), Message::Int64(2)])
+);
+
+macro_rules! wide_round_trip {
+	($name:ident, $encoding:expr, $value:expr, $type:ty, $expected:expr) => {
+		#[test]
+		fn $name() {
+			let initial: $type = $value;
+			let encoded = SerializerConfig::new()
+				.wide_integer_encoding($encoding)
+				.serialize(&initial)
+				.expect("failed to serialize");
+			assert_eq!(encoded, $expected);
+			let decoded = DeserializerConfig::new()
+				.wide_integer_encoding($encoding)
+				.deserialize::<$type>(encoded)
+				.expect("failed to deserialize");
+			assert_eq!(decoded, initial);
+		}
+	};
+}
+
+wide_round_trip!(
+	wide_data_i128,
+	WideIntegerEncoding::Data,
+	i128::MIN,
+	i128,
+	Message::Data({
+		let mut bytes = vec![0; 16];
+		bytes[0] = 0x80;
+		bytes
+	})
+);
+wide_round_trip!(
+	wide_string_i128,
+	WideIntegerEncoding::String,
+	-170141183460469231731687303715884105728,
+	i128,
+	Message::String(CString::new("-170141183460469231731687303715884105728").unwrap())
+);
+wide_round_trip!(
+	wide_string_u128,
+	WideIntegerEncoding::String,
+	u128::MAX,
+	u128,
+	Message::String(CString::new("340282366920938463463374607431768211455").unwrap())
+);
+wide_round_trip!(
+	wide_halves_u128,
+	WideIntegerEncoding::Halves,
+	(1u128 << 64) | 42,
+	u128,
+	Message::Array(vec![Message::Uint64(1), Message::Uint64(42)])
+);
+wide_round_trip!(
+	wide_halves_i128,
+	WideIntegerEncoding::Halves,
+	-1,
+	i128,
+	Message::Array(vec![Message::Uint64(u64::MAX), Message::Uint64(u64::MAX)])
+);
+
+in_range!(wide_from_uint64, u128, Message::Uint64(42), 42);
+in_range!(wide_from_int64, i128, Message::Int64(-42), -42);
+out_of_range!(wide_unsigned_from_negative, u128, Message::Int64(-1), -1);
+
+#[test]
+fn wide_data_size_mismatch() {
+	match xpc_serde::deserialize::<u128>(Message::Data(vec![0; 8])) {
+		Err(DeserializeError::InvalidLength {
+			expected: 16,
+			got: 8,
+		}) => {}
+		other => panic!("expected InvalidLength, got {:?}", other),
+	}
+}
+
+#[test]
+fn wide_halves_size_mismatch() {
+	let config = DeserializerConfig::new().wide_integer_encoding(WideIntegerEncoding::Halves);
+	match config.deserialize::<i128>(Message::Array(vec![Message::Uint64(1)])) {
+		Err(DeserializeError::InvalidLength {
+			expected: 2,
+			got: 1,
+		}) => {}
+		other => panic!("expected InvalidLength, got {:?}", other),
+	}
+}
+
+#[test]
+fn wide_string_invalid() {
+	let config = DeserializerConfig::new().wide_integer_encoding(WideIntegerEncoding::String);
+	match config.deserialize::<u128>(Message::String(CString::new("-1").unwrap())) {
+		Err(DeserializeError::InvalidDecimal("u128")) => {}
+		other => panic!("expected InvalidDecimal, got {:?}", other),
+	}
+}
@@ -0,0 +1,259 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+use xpc_serde::{
+	error::{DeserializeError, PathSegment, SerializeError},
+	DeserializerConfig, EnumRepresentation, SerializerConfig,
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+/// Deserializes the message both by value and by reference, checking that
+/// both fail at the same path.
+macro_rules! fails_at {
+	($name:ident, $type:ty, $message:expr, $path:expr) => {
+		fails_at!($name, $type, EnumRepresentation::default(), $message, $path);
+	};
+	($name:ident, $type:ty, $representation:expr, $message:expr, $path:expr) => {
+		#[test]
+		fn $name() {
+			let config = DeserializerConfig::new().enum_representation($representation);
+			let message: Message = $message;
+			let error = config
+				.deserialize_ref::<$type>(&message)
+				.expect_err("deserialized by reference");
+			assert_eq!(error.path().map(ToString::to_string).as_deref(), $path);
+			let error = config
+				.deserialize::<$type>(message)
+				.expect_err("deserialized by value");
+			assert_eq!(error.path().map(ToString::to_string).as_deref(), $path);
+		}
+	};
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Settings {
+	settings: Sources,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Sources {
+	sources: Vec<Source>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Source {
+	port: u16,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum TestEnum {
+	Simple,
+	Single(u8),
+	Struct { a: u8 },
+}
+
+fn source(port: Message) -> Message {
+	Message::Dictionary(dict![cstr!("port") => port])
+}
+
+fn settings(sources: Vec<Message>) -> Message {
+	Message::Dictionary(dict![
+		cstr!("settings") => Message::Dictionary(dict![
+			cstr!("sources") => Message::Array(sources)
+		])
+	])
+}
+
+fails_at!(root, u8, Message::Uint64(256), None);
+fails_at!(
+	array_element,
+	Vec<u8>,
+	Message::Array(vec![Message::Uint64(1), Message::Uint64(256)]),
+	Some("[1]")
+);
+fails_at!(
+	map_value,
+	HashMap<String, u8>,
+	Message::Dictionary(dict![cstr!("a") => Message::Uint64(256)]),
+	Some(".a")
+);
+fails_at!(
+	map_key,
+	HashMap<u8, ()>,
+	Message::Dictionary(dict![cstr!("256") => Message::Null]),
+	Some(".256")
+);
+fails_at!(
+	nested,
+	Settings,
+	settings(vec![
+		source(Message::Uint64(1)),
+		source(Message::Uint64(2)),
+		source(Message::Uint64(3)),
+		source(Message::Int64(-1)),
+	]),
+	Some(".settings.sources[3].port")
+);
+fails_at!(
+	missing_field,
+	Settings,
+	settings(vec![Message::Dictionary(HashMap::new())]),
+	Some(".settings.sources[0]")
+);
+fails_at!(
+	externally_tagged_newtype,
+	Vec<TestEnum>,
+	Message::Array(vec![
+		Message::String(cstr!("Simple")),
+		Message::Dictionary(dict![cstr!("Single") => Message::Uint64(256)])
+	]),
+	Some("[1].Single")
+);
+fails_at!(
+	externally_tagged_struct,
+	TestEnum,
+	Message::Dictionary(dict![
+		cstr!("Struct") => Message::Dictionary(dict![cstr!("a") => Message::Uint64(256)])
+	]),
+	Some(".Struct.a")
+);
+fails_at!(
+	externally_tagged_unit,
+	TestEnum,
+	Message::Dictionary(dict![cstr!("Simple") => Message::Uint64(1)]),
+	Some(".Simple")
+);
+fails_at!(
+	type_value,
+	TestEnum,
+	EnumRepresentation::TypeValue,
+	Message::Dictionary(dict![
+		cstr!("type") => Message::String(cstr!("Single")),
+		cstr!("value") => Message::Uint64(256)
+	]),
+	Some(".value")
+);
+fails_at!(
+	unknown_variant,
+	Vec<TestEnum>,
+	Message::Array(vec![Message::String(cstr!("Unknown"))]),
+	Some("[0]")
+);
+
+#[test]
+fn display_includes_path() {
+	let error = xpc_serde::deserialize::<Settings>(settings(vec![source(Message::Int64(-1))]))
+		.expect_err("deserialized a negative port");
+	assert_eq!(
+		error.to_string(),
+		"-1 is out of range for u16 at .settings.sources[0].port"
+	);
+}
+
+#[test]
+fn path_segments() {
+	let error = xpc_serde::deserialize::<Settings>(settings(vec![source(Message::Int64(-1))]))
+		.expect_err("deserialized a negative port");
+	assert_eq!(
+		error.path().expect("error has no path").segments(),
+		&[
+			PathSegment::Key("settings".to_owned()),
+			PathSegment::Key("sources".to_owned()),
+			PathSegment::Index(0),
+			PathSegment::Key("port".to_owned()),
+		]
+	);
+	assert!(matches!(
+		error.inner(),
+		DeserializeError::OutOfRange {
+			expected: "u16",
+			value: -1
+		}
+	));
+}
+
+#[derive(Debug, Serialize)]
+enum Nul {
+	Single(&'static str),
+	Tuple(u8, &'static str),
+	Struct { a: &'static str },
+}
+
+#[derive(Debug, Serialize)]
+struct NulKey {
+	#[serde(rename = "a\0b")]
+	a: u8,
+}
+
+/// Serializes the value, checking that it fails with a NUL byte error at the
+/// given path.
+macro_rules! nul_at {
+	($name:ident, $value:expr, $path:expr) => {
+		nul_at!($name, EnumRepresentation::default(), $value, $path);
+	};
+	($name:ident, $representation:expr, $value:expr, $path:expr) => {
+		#[test]
+		fn $name() {
+			let error = SerializerConfig::new()
+				.enum_representation($representation)
+				.serialize(&$value)
+				.expect_err("serialized an interior NUL byte");
+			assert!(matches!(error.inner(), SerializeError::NulString(_)));
+			assert_eq!(error.path().map(ToString::to_string).as_deref(), $path);
+		}
+	};
+}
+
+nul_at!(serialize_root, "a\0b", None);
+nul_at!(serialize_array_element, vec!["a", "a\0b"], Some("[1]"));
+nul_at!(serialize_tuple_element, ("a", 1, "a\0b"), Some("[2]"));
+nul_at!(
+	serialize_map_value,
+	dict!["a" => vec!["a\0b"]],
+	Some(".a[0]")
+);
+nul_at!(serialize_struct_key, NulKey { a: 1 }, Some(".a\0b"));
+nul_at!(serialize_map_key, dict!["a\0b" => 1], Some(".a\0b"));
+nul_at!(
+	serialize_nested_map_key,
+	vec![HashMap::new(), dict!["a\0b" => 1]],
+	Some("[1].a\0b")
+);
+nul_at!(
+	serialize_newtype_variant,
+	Nul::Single("a\0b"),
+	Some(".Single")
+);
+nul_at!(
+	serialize_tuple_variant,
+	Nul::Tuple(1, "a\0b"),
+	Some(".Tuple[1]")
+);
+nul_at!(
+	serialize_struct_variant,
+	vec![Nul::Struct { a: "" }, Nul::Struct { a: "a\0b" }],
+	Some("[1].Struct.a")
+);
+nul_at!(
+	serialize_type_value_variant,
+	EnumRepresentation::TypeValue,
+	Nul::Struct { a: "a\0b" },
+	Some(".value.a")
+);
@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::{Message, MessageError};
+use xpc_serde::{
+	error::{DeserializeError, XpcPeerError},
+	DeserializerConfig, EnumRepresentation, SerializerConfig,
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Reply {
+	r#type: String,
+	count: u64,
+}
+
+fn reply() -> Message {
+	Message::Dictionary(dict![
+		cstr!("type") => Message::String(cstr!("status")),
+		cstr!("count") => Message::Uint64(3)
+	])
+}
+
+#[test]
+fn peer_error() {
+	assert_eq!(
+		xpc_serde::deserialize::<XpcPeerError>(Message::Error(MessageError::ConnectionInterrupted))
+			.unwrap(),
+		XpcPeerError::ConnectionInterrupted
+	);
+	assert_eq!(
+		xpc_serde::from_message_ref::<XpcPeerError>(&Message::Error(
+			MessageError::ConnectionInvalid
+		))
+		.unwrap(),
+		XpcPeerError::ConnectionInvalid
+	);
+}
+
+#[test]
+fn from_message_error() {
+	assert_eq!(
+		XpcPeerError::from(MessageError::ConnectionInvalid),
+		XpcPeerError::ConnectionInvalid
+	);
+	assert_eq!(
+		XpcPeerError::ConnectionInterrupted.to_string(),
+		"Connection interrupted"
+	);
+	assert_eq!(
+		XpcPeerError::ConnectionInvalid.to_string(),
+		"Connection invalid"
+	);
+	assert_eq!(
+		XpcPeerError::TerminationImminent.to_string(),
+		"Termination imminent"
+	);
+	for error in [
+		XpcPeerError::ConnectionInterrupted,
+		XpcPeerError::ConnectionInvalid,
+		XpcPeerError::TerminationImminent,
+	] {
+		assert_eq!(error.description(), error.to_string());
+	}
+}
+
+#[test]
+fn reply_err() {
+	let message = Message::Error(MessageError::ConnectionInterrupted);
+	assert_eq!(
+		xpc_serde::deserialize_reply::<Reply>(message).unwrap(),
+		Err(XpcPeerError::ConnectionInterrupted)
+	);
+}
+
+#[test]
+fn reply_ok() {
+	for enum_representation in [
+		EnumRepresentation::ExternallyTagged,
+		EnumRepresentation::Index,
+		EnumRepresentation::TypeValue,
+	] {
+		let config = DeserializerConfig::new().enum_representation(enum_representation);
+		assert_eq!(
+			config.deserialize_reply::<Reply>(reply()).unwrap(),
+			Ok(Reply {
+				r#type: "status".to_string(),
+				count: 3,
+			})
+		);
+	}
+	assert_eq!(
+		xpc_serde::deserialize_reply::<u64>(Message::Uint64(1)).unwrap(),
+		Ok(1)
+	);
+}
+
+#[test]
+fn reply_ok_invalid() {
+	let error = xpc_serde::deserialize_reply::<u64>(Message::String(cstr!("one"))).unwrap_err();
+	assert!(matches!(
+		error,
+		DeserializeError::Unexpected("u64", "string")
+	));
+	assert!(error.path().is_none());
+}
+
+#[test]
+fn reply_result() {
+	// A reply that is itself a Result keeps its own tagging.
+	let message = xpc_serde::serialize(&Err::<u64, String>("failed".to_string())).unwrap();
+	assert_eq!(
+		xpc_serde::deserialize_reply::<Result<u64, String>>(message).unwrap(),
+		Ok(Err("failed".to_string()))
+	);
+}
+
+#[test]
+fn untagged_results_rejected() {
+	assert!(
+		xpc_serde::deserialize::<Result<String, String>>(Message::String(cstr!("foo"))).is_err()
+	);
+	assert!(xpc_serde::from_message_ref::<Result<u64, XpcPeerError>>(&Message::Uint64(1)).is_err());
+	// deserialize_reply reads these instead; see reply_ok and reply_err.
+	assert!(
+		xpc_serde::deserialize::<Result<u64, XpcPeerError>>(Message::Error(
+			MessageError::ConnectionInvalid
+		))
+		.is_err()
+	);
+}
+
+#[test]
+fn serialized_results_round_trip() {
+	for enum_representation in [
+		EnumRepresentation::ExternallyTagged,
+		EnumRepresentation::Index,
+		EnumRepresentation::TypeValue,
+	] {
+		let ser = SerializerConfig::new().enum_representation(enum_representation);
+		let de = DeserializerConfig::new().enum_representation(enum_representation);
+		for result in [
+			Ok(7u64),
+			Err(XpcPeerError::ConnectionInvalid),
+			Err(XpcPeerError::ConnectionInterrupted),
+			Err(XpcPeerError::TerminationImminent),
+		] {
+			let message = ser.serialize(&result).unwrap();
+			assert_eq!(
+				de.deserialize::<Result<u64, XpcPeerError>>(message)
+					.unwrap(),
+				result,
+				"{:?}",
+				enum_representation
+			);
+		}
+	}
+}
+
+#[test]
+fn other_results_unaffected() {
+	let message = xpc_serde::serialize(&Err::<u64, String>("failed".to_string())).unwrap();
+	assert_eq!(
+		xpc_serde::deserialize::<Result<u64, String>>(message).unwrap(),
+		Err("failed".to_string())
+	);
+}
+
+#[test]
+fn error_into_other_types() {
+	assert!(matches!(
+		xpc_serde::deserialize::<u64>(Message::Error(MessageError::ConnectionInvalid)),
+		Err(DeserializeError::Unexpected("u64", "error"))
+	));
+}
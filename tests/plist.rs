@@ -0,0 +1,200 @@
+#![cfg(feature = "plist")]
+
+use bytes::Bytes;
+use plist::{Date, Dictionary, Value};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::HashMap,
+	ffi::CString,
+	io::Cursor,
+	time::{Duration, UNIX_EPOCH},
+};
+use xpc_connection::Message;
+use xpc_serde::{
+	error::PlistError,
+	plist::{from_plist, to_plist},
+	Uuid,
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+/// Writes the value as a plist with the given writer function, then reads it
+/// back.
+macro_rules! plist_round_trip {
+	($name:ident, $writer:path) => {
+		#[test]
+		fn $name() {
+			let mut buffer = Vec::new();
+			$writer(&mut buffer, &config()).expect("failed to write plist");
+			let decoded: Config =
+				xpc_serde::plist::from_reader(Cursor::new(buffer)).expect("failed to read plist");
+			assert_eq!(decoded, config());
+		}
+	};
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Config {
+	label: String,
+	program_arguments: Vec<String>,
+	run_at_load: bool,
+	throttle_interval: u32,
+	nice: i8,
+	ratio: f64,
+	token: Bytes,
+	session: Uuid,
+	user_name: Option<String>,
+}
+
+fn config() -> Config {
+	Config {
+		label: "com.example.helper".to_owned(),
+		program_arguments: vec!["/usr/libexec/helper".to_owned(), "--verbose".to_owned()],
+		run_at_load: true,
+		throttle_interval: 10,
+		nice: -5,
+		ratio: 0.25,
+		token: Bytes::from_static(&[0xde, 0xad]),
+		session: "00010203-0405-0607-0809-0a0b0c0d0e0f".parse().unwrap(),
+		user_name: None,
+	}
+}
+
+plist_round_trip!(xml_round_trip, xpc_serde::plist::to_writer_xml);
+plist_round_trip!(binary_round_trip, xpc_serde::plist::to_writer_binary);
+
+#[test]
+fn file_round_trip() {
+	let path = std::env::temp_dir().join(format!("xpc-serde-{}.plist", std::process::id()));
+	xpc_serde::plist::to_file_binary(&path, &config()).expect("failed to write plist file");
+	let decoded: Config = xpc_serde::plist::from_file(&path).expect("failed to read plist file");
+	std::fs::remove_file(&path).expect("failed to remove plist file");
+	assert_eq!(decoded, config());
+}
+
+#[test]
+fn reads_xml() {
+	let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>label</key>
+	<string>com.example.helper</string>
+	<key>program_arguments</key>
+	<array>
+		<string>/usr/libexec/helper</string>
+		<string>--verbose</string>
+	</array>
+	<key>run_at_load</key>
+	<true/>
+	<key>throttle_interval</key>
+	<integer>10</integer>
+	<key>nice</key>
+	<integer>-5</integer>
+	<key>ratio</key>
+	<real>0.25</real>
+	<key>token</key>
+	<data>3q0=</data>
+	<key>session</key>
+	<string>00010203-0405-0607-0809-0a0b0c0d0e0f</string>
+</dict>
+</plist>"#;
+	let decoded: Config =
+		xpc_serde::plist::from_reader(Cursor::new(xml)).expect("failed to read plist");
+	assert_eq!(decoded, config());
+}
+
+#[test]
+fn message_to_plist() {
+	let message = Message::Dictionary(dict![
+		cstr!("a") => Message::Int64(-1),
+		cstr!("b") => Message::Uint64(u64::MAX),
+		cstr!("c") => Message::Array(vec![Message::Bool(false), Message::Double(1.5)]),
+		cstr!("d") => Message::Data(vec![1]),
+		cstr!("e") => Message::Null
+	]);
+	let mut expected = Dictionary::new();
+	expected.insert("a".to_owned(), Value::Integer((-1).into()));
+	expected.insert("b".to_owned(), Value::Integer(u64::MAX.into()));
+	expected.insert(
+		"c".to_owned(),
+		Value::Array(vec![Value::Boolean(false), Value::Real(1.5)]),
+	);
+	expected.insert("d".to_owned(), Value::Data(vec![1]));
+	let value = to_plist(&message).expect("failed to convert to plist");
+	assert_eq!(value, Value::Dictionary(expected));
+}
+
+#[test]
+fn integers_lose_signedness() {
+	let value = to_plist(&Message::Uint64(1)).expect("failed to convert to plist");
+	assert_eq!(
+		from_plist(&value).expect("failed to convert from plist"),
+		Message::Int64(1)
+	);
+	let value = to_plist(&Message::Uint64(u64::MAX)).expect("failed to convert to plist");
+	assert_eq!(
+		from_plist(&value).expect("failed to convert from plist"),
+		Message::Uint64(u64::MAX)
+	);
+}
+
+#[test]
+fn dates() {
+	let date = Date::from(UNIX_EPOCH + Duration::from_secs(1_000_000_000));
+	assert_eq!(
+		from_plist(&Value::Date(date)).expect("failed to convert from plist"),
+		Message::Int64(1_000_000_000_000_000_000)
+	);
+	let date = Date::from(UNIX_EPOCH - Duration::from_secs(1));
+	assert_eq!(
+		from_plist(&Value::Date(date)).expect("failed to convert from plist"),
+		Message::Int64(-1_000_000_000)
+	);
+}
+
+#[test]
+fn uuids() {
+	assert_eq!(
+		to_plist(&Message::Uuid((0..16).collect())).expect("failed to convert to plist"),
+		Value::String("00010203-0405-0607-0809-0a0b0c0d0e0f".to_owned())
+	);
+	assert!(matches!(
+		to_plist(&Message::Uuid(vec![0; 3])),
+		Err(PlistError::InvalidUuid(3))
+	));
+}
+
+#[test]
+fn null_outside_dictionary() {
+	assert!(matches!(
+		to_plist(&Message::Array(vec![Message::Null])),
+		Err(PlistError::Unsupported("null"))
+	));
+}
+
+#[test]
+fn nul_in_key() {
+	let mut dictionary = Dictionary::new();
+	dictionary.insert("a\0b".to_owned(), Value::Boolean(true));
+	assert!(matches!(
+		from_plist(&Value::Dictionary(dictionary)),
+		Err(PlistError::NulString(_))
+	));
+}
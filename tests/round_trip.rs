@@ -57,6 +57,18 @@ round_trip!(round_trip_u32, 42, u32, Message::Uint64(42));
 round_trip!(round_trip_i32, 42, i32, Message::Int64(42));
 round_trip!(round_trip_u64, 42, u64, Message::Uint64(42));
 round_trip!(round_trip_i64, 42, i64, Message::Int64(42));
+round_trip!(
+	round_trip_u128,
+	u128::MAX - 1,
+	u128,
+	Message::Data((u128::MAX - 1).to_be_bytes().to_vec())
+);
+round_trip!(
+	round_trip_i128,
+	-2,
+	i128,
+	Message::Data((-2i128).to_be_bytes().to_vec())
+);
 round_trip![round_trip_f32, 42.123, f32];
 round_trip![round_trip_f64, 42.123456789, f64];
 round_trip!(
@@ -259,3 +271,52 @@ round_trip!(
 		])
 	])
 );
+
+round_trip!(
+	round_trip_int_keys,
+	dict![1 => "foo".to_string(), -2 => "bar".to_string()],
+	HashMap<i32, String>,
+	Message::Dictionary(dict![
+		cstr!("1") => Message::String(cstr!("foo")),
+		cstr!("-2") => Message::String(cstr!("bar"))
+	])
+);
+round_trip!(
+	round_trip_bool_keys,
+	dict![true => 1, false => 0],
+	HashMap<bool, u64>,
+	Message::Dictionary(dict![
+		cstr!("true") => Message::Uint64(1),
+		cstr!("false") => Message::Uint64(0)
+	])
+);
+round_trip!(
+	round_trip_char_keys,
+	dict!['a' => 1],
+	HashMap<char, u64>,
+	Message::Dictionary(dict![cstr!("a") => Message::Uint64(1)])
+);
+
+#[derive(Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+enum KeyEnum {
+	Foo,
+	Bar,
+}
+round_trip!(
+	round_trip_enum_keys,
+	dict![KeyEnum::Foo => 1, KeyEnum::Bar => 2],
+	HashMap<KeyEnum, u64>,
+	Message::Dictionary(dict![
+		cstr!("Foo") => Message::Uint64(1),
+		cstr!("Bar") => Message::Uint64(2)
+	])
+);
+
+#[test]
+fn invalid_key() {
+	let map = dict![(1, 2) => "foo".to_string()];
+	match xpc_serde::serialize(&map) {
+		Err(xpc_serde::error::SerializeError::InvalidKey("tuple")) => {}
+		other => panic!("expected InvalidKey, got {:?}", other),
+	}
+}
@@ -0,0 +1,315 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::CString, thread};
+use xpc_connection::{Message, MessageError};
+use xpc_serde::{
+	error::{RpcError, XpcPeerError},
+	rpc::{loopback, Client, Server, Transport, ERROR_KEY, ID_KEY},
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Echo {
+	text: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Reply {
+	text: String,
+	length: u64,
+}
+
+fn echo(request: Echo) -> Reply {
+	Reply {
+		length: request.text.len() as u64,
+		text: request.text,
+	}
+}
+
+#[test]
+fn call() {
+	let (client, server) = loopback();
+	let server = thread::spawn(move || Server::new(server).serve(echo));
+
+	let mut client = Client::new(client);
+	for text in &["hello", "", "world"] {
+		let reply: Reply = client
+			.call(&Echo {
+				text: text.to_string(),
+			})
+			.unwrap();
+		assert_eq!(
+			reply,
+			Reply {
+				text: text.to_string(),
+				length: text.len() as u64
+			}
+		);
+	}
+
+	drop(client);
+	server.join().unwrap().unwrap();
+}
+
+#[test]
+fn wire_layout() {
+	assert_eq!(ID_KEY, "rpc_id");
+	let (client, mut server) = loopback();
+	let mut client = Client::new(client);
+	client
+		.send(&Echo {
+			text: "a".to_string(),
+		})
+		.unwrap();
+	client
+		.send(&Echo {
+			text: "b".to_string(),
+		})
+		.unwrap();
+	assert_eq!(
+		server.receive(),
+		Some(Message::Dictionary(dict![
+			cstr!("text") => Message::String(cstr!("a")),
+			cstr!("rpc_id") => Message::Uint64(0)
+		]))
+	);
+	assert_eq!(
+		server.receive(),
+		Some(Message::Dictionary(dict![
+			cstr!("text") => Message::String(cstr!("b")),
+			cstr!("rpc_id") => Message::Uint64(1)
+		]))
+	);
+}
+
+#[test]
+fn out_of_order_replies() {
+	let (client, mut server) = loopback();
+	let mut client = Client::new(client);
+	let first = client
+		.send(&Echo {
+			text: "a".to_string(),
+		})
+		.unwrap();
+	let second = client
+		.send(&Echo {
+			text: "bb".to_string(),
+		})
+		.unwrap();
+
+	// Answer the second request first.
+	for (id, text) in [(second, "bb"), (first, "a")] {
+		server.send(Message::Dictionary(dict![
+			cstr!("text") => Message::String(CString::new(text).unwrap()),
+			cstr!("length") => Message::Uint64(text.len() as u64),
+			cstr!("rpc_id") => Message::Uint64(id)
+		]));
+	}
+
+	let reply: Reply = client.receive(first).unwrap();
+	assert_eq!(reply.text, "a");
+	let reply: Reply = client.receive(second).unwrap();
+	assert_eq!(reply.text, "bb");
+}
+
+#[test]
+fn handle_one_until_closed() {
+	let (client, server) = loopback();
+	let mut server = Server::new(server);
+	let mut client = Client::new(client);
+	let id = client
+		.send(&Echo {
+			text: "a".to_string(),
+		})
+		.unwrap();
+	assert!(server.handle_one(echo).unwrap());
+	assert_eq!(client.receive::<Reply>(id).unwrap().length, 1);
+	drop(client);
+	assert!(!server.handle_one(echo).unwrap());
+}
+
+#[test]
+fn peer_error() {
+	let (client, mut server) = loopback();
+	let mut client = Client::new(client);
+	server.send(Message::Error(MessageError::ConnectionInterrupted));
+	assert!(matches!(
+		client.call::<_, Reply>(&Echo {
+			text: "a".to_string()
+		}),
+		Err(RpcError::Peer(XpcPeerError::ConnectionInterrupted))
+	));
+}
+
+#[test]
+fn closed() {
+	let (client, server) = loopback();
+	drop(server);
+	let mut client = Client::new(client);
+	assert!(matches!(
+		client.call::<_, Reply>(&Echo {
+			text: "a".to_string()
+		}),
+		Err(RpcError::Closed)
+	));
+}
+
+#[test]
+fn requests_must_be_dictionaries() {
+	let (client, _server) = loopback();
+	let mut client = Client::new(client);
+	assert!(matches!(
+		client.call::<_, Reply>(&1u64),
+		Err(RpcError::NotADictionary("u64"))
+	));
+}
+
+#[test]
+fn id_key_is_reserved() {
+	#[derive(Serialize)]
+	struct Request {
+		rpc_id: u64,
+	}
+
+	let (client, _server) = loopback();
+	let mut client = Client::new(client);
+	assert!(matches!(
+		client.send(&Request { rpc_id: 5 }),
+		Err(RpcError::ReservedKey("rpc_id"))
+	));
+}
+
+#[test]
+fn server_rejects_missing_id() {
+	let (mut client, server) = loopback();
+	client.send(Message::Dictionary(dict![
+		cstr!("text") => Message::String(cstr!("a"))
+	]));
+	assert!(matches!(
+		Server::new(server).handle_one(echo),
+		Err(RpcError::MissingId)
+	));
+	assert!(matches!(
+		Client::new(&mut client).receive::<Reply>(0),
+		Err(RpcError::Remote(description)) if description.contains("no request id")
+	));
+}
+
+#[test]
+fn int64_ids() {
+	let (mut client, server) = loopback();
+	client.send(Message::Dictionary(dict![
+		cstr!("text") => Message::String(cstr!("ab")),
+		cstr!("rpc_id") => Message::Int64(3)
+	]));
+	assert!(Server::new(server).handle_one(echo).unwrap());
+	assert_eq!(
+		Client::new(&mut client).receive::<Reply>(3).unwrap(),
+		Reply {
+			text: "ab".to_string(),
+			length: 2,
+		}
+	);
+
+	let (mut server, client) = loopback();
+	server.send(Message::Dictionary(dict![
+		cstr!("text") => Message::String(cstr!("a")),
+		cstr!("length") => Message::Uint64(1),
+		cstr!("rpc_id") => Message::Int64(0)
+	]));
+	assert_eq!(Client::new(client).receive::<Reply>(0).unwrap().length, 1);
+}
+
+#[test]
+fn server_rejects_invalid_ids() {
+	for (id, kind) in [
+		(Message::Int64(-1), "negative i64"),
+		(Message::String(cstr!("1")), "string"),
+	] {
+		let (mut client, server) = loopback();
+		client.send(Message::Dictionary(dict![
+			cstr!("text") => Message::String(cstr!("a")),
+			cstr!("rpc_id") => id
+		]));
+		assert!(matches!(
+			Server::new(server).handle_one(echo),
+			Err(RpcError::InvalidId(error)) if error == kind
+		));
+		assert!(matches!(
+			Client::new(&mut client).receive::<Reply>(0),
+			Err(RpcError::Remote(description)) if description.contains("non-negative integers")
+		));
+	}
+}
+
+#[test]
+fn server_reports_bad_requests() {
+	let (mut client, server) = loopback();
+	client.send(Message::Dictionary(dict![
+		cstr!("wrong") => Message::Null,
+		cstr!("rpc_id") => Message::Uint64(0)
+	]));
+	assert!(matches!(
+		Server::new(server).handle_one(echo),
+		Err(RpcError::Deserialize(_))
+	));
+	assert!(matches!(
+		Client::new(&mut client).receive::<Reply>(0),
+		Err(RpcError::Remote(description)) if description.contains("text")
+	));
+}
+
+#[test]
+fn serve_skips_bad_requests() {
+	let (mut client, server) = loopback();
+	let server = thread::spawn(move || Server::new(server).serve(echo));
+	client.send(Message::Uint64(1));
+	client.send(Message::Dictionary(dict![
+		cstr!("wrong") => Message::Null,
+		cstr!("rpc_id") => Message::Uint64(7)
+	]));
+
+	let mut client = Client::new(client);
+	assert!(matches!(
+		client.receive::<Reply>(0),
+		Err(RpcError::Remote(description)) if description.contains("u64")
+	));
+	let reply = client.call::<_, Reply>(&Echo {
+		text: "a".to_string(),
+	});
+	assert!(matches!(
+		client.receive::<Reply>(7),
+		Err(RpcError::Remote(_))
+	));
+	assert_eq!(reply.unwrap().length, 1);
+
+	drop(client);
+	server.join().unwrap().unwrap();
+}
+
+#[test]
+fn error_key_is_reserved() {
+	assert_eq!(ERROR_KEY, "rpc_error");
+	let (client, _server) = loopback();
+	let mut client = Client::new(client);
+	assert!(matches!(
+		client.send(&dict!["rpc_error" => 1u64]),
+		Err(RpcError::ReservedKey("rpc_error"))
+	));
+}
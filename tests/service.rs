@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use std::{cell::Cell, collections::HashMap, ffi::CString, thread};
+use xpc_connection::Message;
+use xpc_serde::{
+	error::RpcError,
+	rpc::{loopback, Client, Transport},
+	xpc_service,
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum IndexError {
+	NotFound(String),
+	Busy,
+}
+
+/// Keeps an index of files.
+#[xpc_service]
+pub trait Indexer {
+	/// Reindexes everything under `path`, returning the number of files.
+	fn reindex(&self, path: String) -> Result<u64, IndexError>;
+	fn search(&self, query: String, limit: u64) -> Vec<String>;
+	fn ping(&self);
+}
+
+#[derive(Default)]
+struct Files {
+	pings: Cell<u64>,
+}
+
+impl Indexer for Files {
+	fn reindex(&self, path: String) -> Result<u64, IndexError> {
+		match path.as_str() {
+			"/busy" => Err(IndexError::Busy),
+			"/missing" => Err(IndexError::NotFound(path)),
+			_ => Ok(path.len() as u64),
+		}
+	}
+
+	fn search(&self, query: String, limit: u64) -> Vec<String> {
+		(0..limit).map(|i| format!("{}{}", query, i)).collect()
+	}
+
+	fn ping(&self) {
+		self.pings.set(self.pings.get() + 1);
+	}
+}
+
+#[test]
+fn calls() {
+	let (client, server) = loopback();
+	let server = thread::spawn(move || {
+		let mut server = IndexerServer::new(server, Files::default());
+		server.serve().unwrap();
+		server.into_inner().1.pings.get()
+	});
+
+	let mut client = IndexerClient::new(client);
+	assert_eq!(client.reindex("/tmp".to_string()).unwrap(), Ok(4));
+	assert_eq!(
+		client.reindex("/busy".to_string()).unwrap(),
+		Err(IndexError::Busy)
+	);
+	assert_eq!(
+		client.reindex("/missing".to_string()).unwrap(),
+		Err(IndexError::NotFound("/missing".to_string()))
+	);
+	assert_eq!(
+		client.search("a".to_string(), 2).unwrap(),
+		vec!["a0".to_string(), "a1".to_string()]
+	);
+	client.ping().unwrap();
+	client.ping().unwrap();
+
+	drop(client);
+	assert_eq!(server.join().unwrap(), 2);
+}
+
+#[test]
+fn wire_layout() {
+	let (client, mut server) = loopback();
+	let mut client = IndexerClient::new(client);
+	server.send(Message::Dictionary(dict![
+		cstr!("search") => Message::Array(vec![Message::String(cstr!("a0"))]),
+		cstr!("rpc_id") => Message::Uint64(0)
+	]));
+	assert_eq!(
+		client.search("a".to_string(), 1).unwrap(),
+		vec!["a0".to_string()]
+	);
+	assert_eq!(
+		server.receive(),
+		Some(Message::Dictionary(dict![
+			cstr!("search") => Message::Dictionary(dict![
+				cstr!("query") => Message::String(cstr!("a")),
+				cstr!("limit") => Message::Uint64(1)
+			]),
+			cstr!("rpc_id") => Message::Uint64(0)
+		]))
+	);
+}
+
+#[test]
+fn unexpected_reply() {
+	let (client, mut server) = loopback();
+	let mut client = IndexerClient::new(client);
+	server.send(Message::Dictionary(dict![
+		cstr!("ping") => Message::Null,
+		cstr!("rpc_id") => Message::Uint64(0)
+	]));
+	assert!(matches!(
+		client.reindex("/tmp".to_string()),
+		Err(RpcError::UnexpectedReply)
+	));
+}
+
+#[test]
+fn unknown_method() {
+	let (mut client, server) = loopback();
+	client.send(Message::Dictionary(dict![
+		cstr!("delete") => Message::Dictionary(HashMap::new()),
+		cstr!("rpc_id") => Message::Uint64(0)
+	]));
+	assert!(matches!(
+		IndexerServer::new(server, Files::default()).handle_one(),
+		Err(RpcError::UnknownMethod(method)) if method == "delete"
+	));
+	assert!(matches!(
+		Client::new(&mut client).receive::<()>(0),
+		Err(RpcError::Remote(description)) if description == "unknown method delete"
+	));
+}
+
+#[test]
+fn bad_arguments() {
+	let (mut client, server) = loopback();
+	client.send(Message::Dictionary(dict![
+		cstr!("search") => Message::Dictionary(dict![
+			cstr!("query") => Message::Uint64(1)
+		]),
+		cstr!("rpc_id") => Message::Uint64(0)
+	]));
+	assert!(matches!(
+		IndexerServer::new(server, Files::default()).handle_one(),
+		Err(RpcError::Deserialize(_))
+	));
+	assert!(matches!(
+		Client::new(&mut client).receive::<()>(0),
+		Err(RpcError::Remote(_))
+	));
+}
+
+#[test]
+fn serve_skips_bad_requests() {
+	let (mut client, server) = loopback();
+	let server = thread::spawn(move || IndexerServer::new(server, Files::default()).serve());
+	client.send(Message::Dictionary(dict![
+		cstr!("delete") => Message::Dictionary(HashMap::new()),
+		cstr!("rpc_id") => Message::Uint64(100)
+	]));
+
+	let mut client = IndexerClient::new(client);
+	assert_eq!(client.reindex("/tmp".to_string()).unwrap(), Ok(4));
+
+	drop(client);
+	server.join().unwrap().unwrap();
+}
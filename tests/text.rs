@@ -0,0 +1,297 @@
+use bytes::Bytes;
+use serde::Serialize;
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+use xpc_serde::{
+	error::ParseErrorKind,
+	text::{parse, print},
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+/// Prints the message, checks the text, then parses it back.
+macro_rules! text_round_trip {
+	($name:ident, $message:expr, $text:expr) => {
+		#[test]
+		fn $name() {
+			let message: Message = $message;
+			let text = print(&message);
+			assert_eq!(text, $text);
+			assert_eq!(parse(&text).expect("failed to parse text"), message);
+		}
+	};
+}
+
+/// Checks that parsing fails at the given line and column.
+macro_rules! fails_at {
+	($name:ident, $text:expr, $line:expr, $column:expr, $kind:pat) => {
+		#[test]
+		fn $name() {
+			let error = parse($text).expect_err("parsing should fail");
+			assert!(matches!(error.kind(), $kind), "unexpected error {}", error);
+			assert_eq!(
+				(error.line(), error.column()),
+				($line, $column),
+				"{}",
+				error
+			);
+		}
+	};
+}
+
+text_round_trip!(null, Message::Null, "null");
+text_round_trip!(bool, Message::Bool(false), "false");
+text_round_trip!(int64, Message::Int64(-42), "-42");
+text_round_trip!(int64_min, Message::Int64(i64::MIN), "-9223372036854775808");
+text_round_trip!(uint64, Message::Uint64(42), "42u");
+text_round_trip!(
+	uint64_max,
+	Message::Uint64(u64::MAX),
+	"18446744073709551615u"
+);
+text_round_trip!(double, Message::Double(1.0), "1.0");
+text_round_trip!(double_negative_zero, Message::Double(-0.0), "-0.0");
+text_round_trip!(double_exponent, Message::Double(1e300), "1e300");
+text_round_trip!(double_small, Message::Double(1.5e-7), "1.5e-7");
+text_round_trip!(double_infinity, Message::Double(f64::INFINITY), "inf");
+text_round_trip!(
+	double_negative_infinity,
+	Message::Double(f64::NEG_INFINITY),
+	"-inf"
+);
+text_round_trip!(
+	string,
+	Message::String(cstr!("say \"hi\"\n\t\\ ünïcode \u{7}")),
+	r#""say \"hi\"\n\t\\ ünïcode \u{7}""#
+);
+text_round_trip!(
+	string_invalid_utf8,
+	Message::String(CString::new(vec![b'a', 0xf0, 0x28, 0x8c, 0xbc, b'b']).unwrap()),
+	r#""a\xf0(\x8c\xbcb""#
+);
+text_round_trip!(data, Message::Data(vec![0x00, 0xab, 0xff]), "<00abff>");
+text_round_trip!(data_empty, Message::Data(vec![]), "<>");
+text_round_trip!(
+	uuid,
+	Message::Uuid((0..16).collect()),
+	"uuid(00010203-0405-0607-0809-0a0b0c0d0e0f)"
+);
+text_round_trip!(array_empty, Message::Array(vec![]), "[]");
+text_round_trip!(dictionary_empty, Message::Dictionary(HashMap::new()), "{}");
+text_round_trip!(
+	nested,
+	Message::Dictionary(dict![
+		cstr!("name") => Message::String(cstr!("main")),
+		cstr!("sources") => Message::Array(vec![
+			Message::Dictionary(dict![cstr!("port") => Message::Uint64(80)]),
+			Message::Array(vec![])
+		]),
+		cstr!("enabled") => Message::Bool(true)
+	]),
+	"{
+	\"enabled\": true,
+	\"name\": \"main\",
+	\"sources\": [
+		{
+			\"port\": 80u,
+		},
+		[],
+	],
+}"
+);
+
+#[test]
+fn nan() {
+	assert_eq!(print(&Message::Double(f64::NAN)), "NaN");
+	match parse("NaN").expect("failed to parse text") {
+		Message::Double(v) => assert!(v.is_nan()),
+		message => panic!("expected a double, got {:?}", message),
+	}
+}
+
+#[test]
+fn serialized_round_trip() {
+	#[derive(Serialize)]
+	enum Kind {
+		Tuple(i8, u32),
+		Struct { inner: Option<String> },
+	}
+
+	#[derive(Serialize)]
+	struct Config {
+		name: String,
+		key: Bytes,
+		weights: Vec<f32>,
+		kinds: Vec<Kind>,
+		parent: Option<Box<Config>>,
+		counts: HashMap<String, u64>,
+	}
+
+	let config = Config {
+		name: "main".to_string(),
+		key: Bytes::from_static(&[1, 2, 3]),
+		weights: vec![0.1, -2.5, f32::MAX],
+		kinds: vec![Kind::Tuple(-1, 2), Kind::Struct { inner: None }],
+		parent: None,
+		counts: [("a".to_string(), 1), ("b".to_string(), u64::MAX)]
+			.iter()
+			.cloned()
+			.collect(),
+	};
+	let message = xpc_serde::serialize(&config).expect("failed to serialize");
+	assert_eq!(
+		parse(&print(&message)).expect("failed to parse text"),
+		message
+	);
+}
+
+#[test]
+fn hand_written() {
+	let text = r#"
+		// A hand-written fixture.
+		{
+			"port": 80u, // trailing comments are fine
+			"offset": -1,
+			"ratio": 5e-1,
+			"key": <00AbfF>,
+			"path": "\u{1F980}\x00\x41"
+		}
+	"#;
+	assert!(matches!(
+		parse(text),
+		Err(error) if matches!(error.kind(), ParseErrorKind::NulInString)
+	));
+	let text = text.replace(r"\x00", "");
+	assert_eq!(
+		parse(&text).expect("failed to parse text"),
+		Message::Dictionary(dict![
+			cstr!("port") => Message::Uint64(80),
+			cstr!("offset") => Message::Int64(-1),
+			cstr!("ratio") => Message::Double(0.5),
+			cstr!("key") => Message::Data(vec![0x00, 0xab, 0xff]),
+			cstr!("path") => Message::String(cstr!("\u{1F980}A"))
+		])
+	);
+}
+
+#[test]
+fn unsupported_kinds_are_invalid() {
+	let text = print(&Message::Array(vec![Message::Uuid(vec![0; 4])]));
+	assert_eq!(text, "[\n\tinvalid,\n]");
+	assert!(parse(&text).is_err());
+}
+
+fails_at!(empty, "", 1, 1, ParseErrorKind::UnexpectedEof);
+fails_at!(trailing, "1 2", 1, 3, ParseErrorKind::TrailingCharacters);
+fails_at!(
+	int64_overflow,
+	"[\n\t9223372036854775808,\n]",
+	2,
+	2,
+	ParseErrorKind::InvalidWord(_)
+);
+fails_at!(negative_uint64, "-1u", 1, 1, ParseErrorKind::InvalidWord(_));
+fails_at!(unknown_word, "[nil]", 1, 2, ParseErrorKind::InvalidWord(_));
+fails_at!(
+	missing_colon,
+	"{\n\t\"a\" 1\n}",
+	2,
+	6,
+	ParseErrorKind::UnexpectedChar('1')
+);
+fails_at!(
+	missing_comma,
+	"[1 2]",
+	1,
+	4,
+	ParseErrorKind::UnexpectedChar('2')
+);
+fails_at!(
+	unquoted_key,
+	"{ a: 1 }",
+	1,
+	3,
+	ParseErrorKind::UnexpectedChar('a')
+);
+fails_at!(
+	duplicate_key,
+	"{\n\t\"a\": 1,\n\t\"a\": 2,\n}",
+	3,
+	2,
+	ParseErrorKind::DuplicateKey(_)
+);
+fails_at!(
+	invalid_escape,
+	"\"ü\\q\"",
+	1,
+	3,
+	ParseErrorKind::InvalidEscape
+);
+fails_at!(
+	signed_byte_escape,
+	"\"a\\x+f\"",
+	1,
+	3,
+	ParseErrorKind::InvalidEscape
+);
+fails_at!(
+	signed_unicode_escape,
+	"\"\\u{+41}\"",
+	1,
+	2,
+	ParseErrorKind::InvalidEscape
+);
+fails_at!(
+	empty_unicode_escape,
+	"\"\\u{}\"",
+	1,
+	2,
+	ParseErrorKind::InvalidEscape
+);
+fails_at!(
+	unterminated_string,
+	"\"abc",
+	1,
+	5,
+	ParseErrorKind::UnexpectedEof
+);
+fails_at!(
+	invalid_uuid,
+	"[\n\tuuid(0001),\n]",
+	2,
+	2,
+	ParseErrorKind::InvalidWord(_)
+);
+fails_at!(odd_data, "<abc>", 1, 1, ParseErrorKind::InvalidData);
+fails_at!(signed_data, "<+f>", 1, 1, ParseErrorKind::InvalidData);
+fails_at!(
+	unterminated_array,
+	"[1,",
+	1,
+	4,
+	ParseErrorKind::UnexpectedEof
+);
+
+#[test]
+fn recursion_limit() {
+	let text = "[".repeat(1000);
+	let error = parse(&text).expect_err("parsing should fail");
+	assert_eq!(error.kind(), &ParseErrorKind::RecursionLimitExceeded);
+	assert_eq!((error.line(), error.column()), (1, 129));
+}
@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+use xpc_serde::{error::DeserializeError, DeserializerConfig, Uuid, UuidCoercion};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+const BYTES: [u8; 16] = [
+	0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+];
+const HYPHENATED: &str = "00010203-0405-0607-0809-0a0b0c0d0e0f";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Session {
+	id: Uuid,
+	parent: Option<Uuid>,
+}
+
+fn lenient() -> DeserializerConfig {
+	DeserializerConfig::new().uuid_coercion(UuidCoercion::Lenient)
+}
+
+#[test]
+fn serializes_as_native_uuid() {
+	let session = Session {
+		id: Uuid::from_bytes(BYTES),
+		parent: None,
+	};
+	let message = Message::Dictionary(dict![
+		cstr!("id") => Message::Uuid(BYTES.to_vec()),
+		cstr!("parent") => Message::Null
+	]);
+	assert_eq!(xpc_serde::serialize(&session).unwrap(), message);
+	assert_eq!(xpc_serde::deserialize::<Session>(message).unwrap(), session);
+}
+
+#[test]
+fn borrowed() {
+	let message = Message::Uuid(BYTES.to_vec());
+	assert_eq!(
+		xpc_serde::from_message_ref::<Uuid>(&message).unwrap(),
+		Uuid::from_bytes(BYTES)
+	);
+}
+
+#[test]
+fn strict_rejects_fallbacks() {
+	for message in [
+		Message::Data(BYTES.to_vec()),
+		Message::String(CString::new(HYPHENATED).unwrap()),
+	] {
+		assert!(matches!(
+			xpc_serde::deserialize::<Uuid>(message),
+			Err(DeserializeError::Unexpected("uuid", _))
+		));
+	}
+}
+
+#[test]
+fn lenient_accepts_fallbacks() {
+	for message in [
+		Message::Uuid(BYTES.to_vec()),
+		Message::Data(BYTES.to_vec()),
+		Message::String(CString::new(HYPHENATED).unwrap()),
+	] {
+		assert_eq!(
+			lenient().deserialize_ref::<Uuid>(&message).unwrap(),
+			Uuid::from_bytes(BYTES)
+		);
+		assert_eq!(
+			lenient().deserialize::<Uuid>(message).unwrap(),
+			Uuid::from_bytes(BYTES)
+		);
+	}
+}
+
+#[test]
+fn lenient_rejects_malformed() {
+	assert!(lenient()
+		.deserialize::<Uuid>(Message::Data(vec![0; 15]))
+		.is_err());
+	assert!(lenient()
+		.deserialize::<Uuid>(Message::String(cstr!("not-a-uuid")))
+		.is_err());
+	assert!(lenient().deserialize::<Uuid>(Message::Int64(0)).is_err());
+}
+
+#[test]
+fn wrong_length() {
+	assert!(xpc_serde::deserialize::<Uuid>(Message::Uuid(vec![0; 4])).is_err());
+}
+
+#[test]
+fn parse_and_display() {
+	let uuid: Uuid = HYPHENATED.parse().unwrap();
+	assert_eq!(uuid.as_bytes(), &BYTES);
+	assert_eq!(uuid.to_string(), HYPHENATED);
+	assert_eq!(
+		"000102030405060708090A0B0C0D0E0F".parse::<Uuid>().unwrap(),
+		uuid
+	);
+	assert_eq!(format!("{:?}", uuid), format!("Uuid({})", HYPHENATED));
+	assert!("00010203-0405-0607-0809_0a0b0c0d0e0f"
+		.parse::<Uuid>()
+		.is_err());
+	assert!("0001020304050607".parse::<Uuid>().is_err());
+	assert!("00010203-0405-0607-0809-0a0b0c0d0e0g"
+		.parse::<Uuid>()
+		.is_err());
+}
+
+#[test]
+fn string_elsewhere() {
+	let uuid = Uuid::from_bytes(BYTES);
+	let json = serde_json::to_string(&uuid).unwrap();
+	assert_eq!(json, format!("\"{}\"", HYPHENATED));
+	assert_eq!(serde_json::from_str::<Uuid>(&json).unwrap(), uuid);
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn uuid_crate() {
+	let uuid = uuid::Uuid::from_bytes(BYTES);
+	assert_eq!(Uuid::from(uuid), Uuid::from_bytes(BYTES));
+	assert_eq!(uuid::Uuid::from(Uuid::from_bytes(BYTES)), uuid);
+}
@@ -0,0 +1,197 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, convert::TryFrom, ffi::CString};
+use xpc_connection::{Message, MessageError};
+use xpc_serde::{error::ValueError, Uuid, Value};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Settings {
+	name: String,
+	sources: Vec<Source>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Source {
+	port: u16,
+	key: Bytes,
+}
+
+fn settings() -> Settings {
+	Settings {
+		name: "main".to_owned(),
+		sources: vec![
+			Source {
+				port: 80,
+				key: Bytes::from_static(&[1, 2]),
+			},
+			Source {
+				port: 443,
+				key: Bytes::new(),
+			},
+		],
+	}
+}
+
+fn every_kind() -> Message {
+	Message::Dictionary(dict![
+		cstr!("null") => Message::Null,
+		cstr!("bool") => Message::Bool(true),
+		cstr!("int64") => Message::Int64(-1),
+		cstr!("uint64") => Message::Uint64(u64::MAX),
+		cstr!("double") => Message::Double(0.5),
+		cstr!("string") => Message::String(cstr!("text")),
+		cstr!("data") => Message::Data(vec![0, 1, 2]),
+		cstr!("uuid") => Message::Uuid((0..16).collect()),
+		cstr!("array") => Message::Array(vec![Message::Uint64(1), Message::Null])
+	])
+}
+
+#[test]
+fn message_round_trip() {
+	let value = Value::try_from(every_kind()).unwrap();
+	let message = Message::from(value);
+	assert_eq!(message, every_kind());
+}
+
+#[test]
+fn non_utf8_string_round_trip() {
+	let string = CString::new(vec![0xf0, 0x28, 0x8c, 0xbc]).expect("failed to create CString");
+	let value = Value::try_from(Message::String(string.clone())).unwrap();
+	assert_eq!(value.as_str(), None);
+	assert_eq!(Message::from(value), Message::String(string));
+}
+
+#[test]
+fn to_and_from_value() {
+	let value = xpc_serde::to_value(&settings()).expect("failed to serialize");
+	assert_eq!(
+		Message::from(value.clone()),
+		xpc_serde::serialize(&settings()).expect("failed to serialize")
+	);
+	let decoded: Settings = xpc_serde::from_value(value).expect("failed to deserialize");
+	assert_eq!(decoded, settings());
+}
+
+#[test]
+fn serialize_deserialize_value() {
+	let value = Value::try_from(every_kind()).unwrap();
+	let message = xpc_serde::serialize(&value).expect("failed to serialize");
+	assert_eq!(message, every_kind());
+	let decoded: Value = xpc_serde::deserialize(message).expect("failed to deserialize");
+	assert_eq!(decoded, value);
+}
+
+#[test]
+fn value_as_field() {
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Envelope {
+		id: u64,
+		payload: Value,
+	}
+
+	let envelope = Envelope {
+		id: 7,
+		payload: Value::try_from(every_kind()).unwrap(),
+	};
+	let message = xpc_serde::serialize(&envelope).expect("failed to serialize");
+	let decoded: Envelope = xpc_serde::from_message_ref(&message).expect("failed to deserialize");
+	assert_eq!(decoded, envelope);
+}
+
+#[test]
+fn index() {
+	let value = xpc_serde::to_value(&settings()).expect("failed to serialize");
+	assert_eq!(value["name"].as_str(), Some("main"));
+	assert_eq!(value["sources"][1]["port"].as_u64(), Some(443));
+	assert_eq!(value["sources"][0]["key"].as_bytes(), Some(&[1, 2][..]));
+	assert!(value["missing"].is_null());
+	assert!(value["sources"][5]["port"].is_null());
+	assert!(value["name"][0].is_null());
+	assert!(value["a\0b"].is_null());
+}
+
+#[test]
+fn get() {
+	let value = xpc_serde::to_value(&settings()).expect("failed to serialize");
+	assert!(value.get("sources").is_some());
+	assert!(value.get("name".to_owned()).is_some());
+	assert_eq!(value.get("missing"), None);
+	assert_eq!(value.get(0), None);
+	assert_eq!(
+		value["sources"].get(1).and_then(|v| v.get("port")),
+		Some(&Value::Uint64(443))
+	);
+}
+
+#[test]
+fn pointer() {
+	let value = xpc_serde::to_value(&settings()).expect("failed to serialize");
+	assert_eq!(value.pointer(""), Some(&value));
+	assert_eq!(
+		value.pointer("/sources/1/port").and_then(Value::as_u64),
+		Some(443)
+	);
+	assert_eq!(value.pointer("/sources/2/port"), None);
+	assert_eq!(value.pointer("/sources/x"), None);
+	assert_eq!(value.pointer("sources"), None);
+
+	let escaped = Value::try_from(Message::Dictionary(dict![
+		cstr!("a/b") => Message::Dictionary(dict![cstr!("c~d") => Message::Bool(true)])
+	]))
+	.unwrap();
+	assert_eq!(escaped.pointer("/a~1b/c~0d"), Some(&Value::Bool(true)));
+}
+
+#[test]
+fn integer_accessors() {
+	assert_eq!(Value::Int64(5).as_u64(), Some(5));
+	assert_eq!(Value::Int64(-5).as_u64(), None);
+	assert_eq!(Value::Uint64(u64::MAX).as_i64(), None);
+	assert_eq!(Value::Uint64(5).as_i64(), Some(5));
+	assert_eq!(Value::Double(5.0).as_u64(), None);
+}
+
+#[test]
+fn uuid() {
+	let uuid: Uuid = "00010203-0405-0607-0809-0a0b0c0d0e0f".parse().unwrap();
+	let value = Value::try_from(Message::Uuid(uuid.as_bytes().to_vec())).unwrap();
+	assert_eq!(value, Value::Uuid(uuid));
+	assert_eq!(value.as_uuid(), Some(uuid));
+	assert_eq!(value.as_bytes(), None);
+	assert_eq!(xpc_serde::to_value(&uuid).unwrap(), value);
+	assert_eq!(xpc_serde::from_value::<Uuid>(value).unwrap(), uuid);
+}
+
+#[test]
+fn unsupported_kinds() {
+	assert_eq!(
+		Value::try_from(Message::Array(vec![Message::Error(
+			MessageError::ConnectionInvalid
+		)])),
+		Err(ValueError::Unsupported("error"))
+	);
+	assert_eq!(
+		Value::try_from(Message::Dictionary(dict![
+			cstr!("uuid") => Message::Uuid(vec![0; 3])
+		])),
+		Err(ValueError::InvalidUuid(3))
+	);
+}
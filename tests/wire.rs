@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, ffi::CString};
+use xpc_connection::Message;
+use xpc_serde::{
+	error::WireError,
+	wire::{decode, encode, from_slice, to_vec},
+};
+
+macro_rules! cstr {
+	($value:expr) => {
+		CString::new($value).expect(concat!("failed to create CString from '", $value, "'"))
+	};
+}
+
+macro_rules! dict {
+	[$($key:expr => $value:expr),*] => {
+		{
+			let mut map = HashMap::new();
+			$(
+				map.insert($key, $value);
+			)*
+			map
+		}
+	};
+}
+
+/// The magic number and version every encoded message starts with.
+const HEADER: [u8; 8] = [0x42, 0x37, 0x13, 0x42, 0x05, 0x00, 0x00, 0x00];
+
+fn with_header(object: &[u8]) -> Vec<u8> {
+	let mut bytes = HEADER.to_vec();
+	bytes.extend_from_slice(object);
+	bytes
+}
+
+/// Encodes the message, checks the object bytes after the header, then
+/// decodes them again.
+macro_rules! wire_round_trip {
+	($name:ident, $message:expr, $object:expr) => {
+		#[test]
+		fn $name() {
+			let message: Message = $message;
+			let bytes = encode(&message).expect("failed to encode");
+			assert_eq!(bytes, with_header(&$object));
+			assert_eq!(decode(&bytes).expect("failed to decode"), message);
+		}
+	};
+}
+
+macro_rules! fails_to_decode {
+	($name:ident, $object:expr, $error:pat) => {
+		#[test]
+		fn $name() {
+			let error = decode(&with_header(&$object)).expect_err("decoding should fail");
+			assert!(matches!(error, $error), "unexpected error {}", error);
+		}
+	};
+}
+
+wire_round_trip!(null, Message::Null, [0x00, 0x10, 0x00, 0x00]);
+wire_round_trip!(
+	bool,
+	Message::Bool(true),
+	[0x00, 0x20, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00]
+);
+wire_round_trip!(
+	int64,
+	Message::Int64(-2),
+	[0x00, 0x30, 0x00, 0x00, 0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+);
+wire_round_trip!(
+	uint64,
+	Message::Uint64(0x0102030405060708),
+	[0x00, 0x40, 0x00, 0x00, 0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]
+);
+wire_round_trip!(
+	double,
+	Message::Double(1.0),
+	[0x00, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f]
+);
+wire_round_trip!(
+	data,
+	Message::Data(vec![0xaa, 0xbb, 0xcc, 0xdd, 0xee]),
+	[
+		0x00, 0x80, 0x00, 0x00, // type
+		0x05, 0x00, 0x00, 0x00, // length
+		0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0x00, 0x00, 0x00, // bytes and padding
+	]
+);
+wire_round_trip!(
+	data_empty,
+	Message::Data(vec![]),
+	[0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+);
+wire_round_trip!(
+	string,
+	Message::String(cstr!("hello")),
+	[
+		0x00, 0x90, 0x00, 0x00, // type
+		0x06, 0x00, 0x00, 0x00, // length, including the NUL
+		b'h', b'e', b'l', b'l', b'o', 0x00, 0x00, 0x00, // bytes, NUL and padding
+	]
+);
+wire_round_trip!(
+	string_aligned,
+	Message::String(cstr!("abc")),
+	[0x00, 0x90, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, b'a', b'b', b'c', 0x00]
+);
+wire_round_trip!(
+	uuid,
+	Message::Uuid((0..16).collect()),
+	[
+		0x00, 0xa0, 0x00, 0x00, // type
+		0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, // bytes
+		0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e, 0x0f,
+	]
+);
+wire_round_trip!(
+	array,
+	Message::Array(vec![Message::Null, Message::Bool(false)]),
+	[
+		0x00, 0xe0, 0x00, 0x00, // type
+		0x10, 0x00, 0x00, 0x00, // length of the rest
+		0x02, 0x00, 0x00, 0x00, // count
+		0x00, 0x10, 0x00, 0x00, // null
+		0x00, 0x20, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // false
+	]
+);
+wire_round_trip!(
+	array_empty,
+	Message::Array(vec![]),
+	[0x00, 0xe0, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+);
+wire_round_trip!(
+	dictionary,
+	Message::Dictionary(dict![
+		cstr!("port") => Message::Uint64(80),
+		cstr!("a") => Message::Null
+	]),
+	[
+		0x00, 0xf0, 0x00, 0x00, // type
+		0x20, 0x00, 0x00, 0x00, // length of the rest
+		0x02, 0x00, 0x00, 0x00, // count
+		b'a', 0x00, 0x00, 0x00, // "a", sorted first
+		0x00, 0x10, 0x00, 0x00, // null
+		b'p', b'o', b'r', b't', 0x00, 0x00, 0x00, 0x00, // "port"
+		0x00, 0x40, 0x00, 0x00, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // 80
+	]
+);
+wire_round_trip!(
+	nested,
+	Message::Dictionary(dict![
+		cstr!("xs") => Message::Array(vec![Message::Dictionary(HashMap::new())])
+	]),
+	[
+		0x00, 0xf0, 0x00, 0x00, // type
+		0x20, 0x00, 0x00, 0x00, // length of the rest
+		0x01, 0x00, 0x00, 0x00, // count
+		b'x', b's', 0x00, 0x00, // "xs"
+		0x00, 0xe0, 0x00, 0x00, // array
+		0x10, 0x00, 0x00, 0x00, // length of the rest
+		0x01, 0x00, 0x00, 0x00, // count
+		0x00, 0xf0, 0x00, 0x00, // dictionary
+		0x04, 0x00, 0x00, 0x00, // length of the rest
+		0x00, 0x00, 0x00, 0x00, // count
+	]
+);
+
+#[test]
+fn date_decodes_to_nanoseconds() {
+	let bytes = with_header(&[
+		0x00, 0x70, 0x00, 0x00, 0x00, 0xca, 0x9a, 0x3b, 0x00, 0x00, 0x00, 0x00,
+	]);
+	assert_eq!(
+		decode(&bytes).expect("failed to decode"),
+		Message::Int64(1_000_000_000)
+	);
+}
+
+#[test]
+fn serde_round_trip() {
+	#[derive(Debug, PartialEq, Serialize, Deserialize)]
+	struct Request {
+		name: String,
+		ids: Vec<u64>,
+		ratio: f64,
+		parent: Option<Box<Request>>,
+	}
+
+	let request = Request {
+		name: "main".to_string(),
+		ids: vec![1, 2, u64::MAX],
+		ratio: -0.5,
+		parent: Some(Box::new(Request {
+			name: "root".to_string(),
+			ids: vec![],
+			ratio: 0.0,
+			parent: None,
+		})),
+	};
+	let bytes = to_vec(&request).expect("failed to encode");
+	assert_eq!(&bytes[..8], &HEADER);
+	assert_eq!(
+		from_slice::<Request>(&bytes).expect("failed to decode"),
+		request
+	);
+}
+
+#[test]
+fn invalid_magic() {
+	assert!(matches!(
+		decode(&[0xde, 0xad, 0xbe, 0xef, 0x05, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00]),
+		Err(WireError::InvalidMagic(0xefbeadde))
+	));
+}
+
+#[test]
+fn unsupported_version() {
+	assert!(matches!(
+		decode(&[0x42, 0x37, 0x13, 0x42, 0x04, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00]),
+		Err(WireError::UnsupportedVersion(4))
+	));
+}
+
+#[test]
+fn encode_invalid_uuid() {
+	assert!(matches!(
+		encode(&Message::Uuid(vec![0; 4])),
+		Err(WireError::InvalidUuid(4))
+	));
+}
+
+fails_to_decode!(empty, [], WireError::UnexpectedEof(8));
+fails_to_decode!(
+	truncated_int64,
+	[0x00, 0x30, 0x00, 0x00, 0x01],
+	WireError::UnexpectedEof(12)
+);
+fails_to_decode!(
+	truncated_data,
+	[0x00, 0x80, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0x00],
+	WireError::UnexpectedEof(16)
+);
+fails_to_decode!(
+	unknown_type,
+	[0x34, 0x12, 0x00, 0x00],
+	WireError::UnknownType {
+		kind: 0x1234,
+		offset: 8
+	}
+);
+fails_to_decode!(
+	fd,
+	[0x00, 0xb0, 0x00, 0x00],
+	WireError::UnsupportedType {
+		kind: "fd",
+		offset: 8
+	}
+);
+fails_to_decode!(
+	string_without_nul,
+	[0x00, 0x90, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, b'a', b'b', b'c', b'd'],
+	WireError::InvalidString(8)
+);
+fails_to_decode!(
+	string_with_inner_nul,
+	[0x00, 0x90, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, b'a', 0x00, b'c', 0x00],
+	WireError::InvalidString(8)
+);
+fails_to_decode!(
+	container_length_mismatch,
+	[
+		0x00, 0xe0, 0x00, 0x00, 0x0c, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00,
+		0x00, 0x00, 0x10, 0x00, 0x00,
+	],
+	WireError::InvalidLength(8)
+);
+fails_to_decode!(
+	container_length_past_end,
+	[0x00, 0xe0, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00],
+	WireError::InvalidLength(8)
+);
+fails_to_decode!(
+	duplicate_key,
+	[
+		0x00, 0xf0, 0x00, 0x00, 0x14, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, b'a', 0x00, 0x00,
+		0x00, 0x00, 0x10, 0x00, 0x00, b'a', 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00,
+	],
+	WireError::DuplicateKey(28)
+);
+fails_to_decode!(
+	trailing_bytes,
+	[0x00, 0x10, 0x00, 0x00, 0x00],
+	WireError::TrailingBytes(12)
+);
+
+#[test]
+fn recursion_limit() {
+	let mut object = Vec::new();
+	for _ in 0..1000 {
+		object.extend_from_slice(&[
+			0x00, 0xe0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+		]);
+	}
+	assert!(matches!(
+		decode(&with_header(&object)),
+		Err(WireError::RecursionLimitExceeded(_))
+	));
+}